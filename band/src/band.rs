@@ -1,28 +1,98 @@
+use super::cancel::{CancelHandle, Running};
+use super::conditions::{self, Condition};
+use super::context::TaskContext;
+use super::events::{self, Event, Reporter, RunId};
+use super::fingerprint::{self, Store};
+use super::history::HistoryStore;
+use super::hooks::{Hooks, SharedAction, Wrapper};
+use super::namespace::{self, Namespace};
+use super::params::{ArgSource, Args, Dependency, Invocation, Param, Value};
+use super::policy::{Backoff, FailurePolicy};
+use super::scheduler::{self, RunSummary};
 use super::Error;
 use futures_util::future::{BoxFuture, FutureExt, TryFutureExt};
-use itertools::Itertools;
-use service::{Rejection, Service};
+use service::{Attempts, Middleware, Rejection, Service};
 use slotmap::{DefaultKey, DenseSlotMap, SecondaryMap};
 use std::collections::{HashMap, HashSet};
-use std::future::Future;
 use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub type Action<C> = Box<
     dyn Service<
-        C,
-        Output = (C, ()),
-        Error = Error,
-        Future = BoxFuture<'static, Result<(C, ()), Rejection<C, Error>>>,
-    >,
+            C,
+            Output = (C, ()),
+            Error = Error,
+            Future = BoxFuture<'static, Result<(C, ()), Rejection<C, Error>>>,
+        > + Send
+        + Sync,
 >;
 
 pub struct TaskDesc<C> {
-    name: String,
-    action: Action<C>,
-    dependencies: Vec<String>,
+    pub(crate) name: String,
+    description: Option<String>,
+    action: Option<Action<TaskContext<C>>>,
+    params: Vec<Param>,
+    dependencies: Vec<Dependency>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    failure_policy: Option<FailurePolicy>,
+    retry: Attempts,
+    timeout: Option<Duration>,
+    conditions: Vec<Condition<C>>,
 }
 
-pub struct ActionBox<A, C>(A, PhantomData<C>)
+impl<C> TaskDesc<C> {
+    /// A task without an action, running `dependencies`.
+    pub(crate) fn group(name: String, description: String, dependencies: Vec<String>) -> Self {
+        TaskDesc {
+            name,
+            description: Some(description),
+            action: None,
+            params: Vec::new(),
+            dependencies: dependencies.into_iter().map(Dependency::new).collect(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            failure_policy: None,
+            retry: Attempts::new(0),
+            timeout: None,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Prefix the name of the task, and its dependencies on tasks in `names`.
+    fn prefixed(mut self, prefix: &str, names: &HashSet<String>) -> Self {
+        let prefix = |name: &str| format!("{}{}{}", prefix, namespace::SEPARATOR, name);
+        self.name = prefix(&self.name);
+        for dep in &mut self.dependencies {
+            if names.contains(&dep.name) {
+                dep.name = prefix(&dep.name);
+            }
+        }
+        self
+    }
+}
+
+pub(crate) struct Task<C> {
+    pub name: String,
+    pub description: Option<String>,
+    /// `None` for groups, which only run their dependencies.
+    pub action: Option<Action<TaskContext<C>>>,
+    pub params: Vec<Param>,
+    /// Arguments passed to dependencies, for dependencies given any.
+    pub dependency_args: Vec<(DefaultKey, Vec<(String, ArgSource)>)>,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub failure_policy: Option<FailurePolicy>,
+    pub retry: Attempts,
+    pub timeout: Option<Duration>,
+    /// Every condition must hold for the task to run.
+    pub conditions: Vec<Condition<C>>,
+}
+
+pub struct ActionBox<A, C>(A, PhantomData<fn(C)>)
 where
     A: Service<C, Output = (C, ())>;
 
@@ -48,153 +118,729 @@ where
     }
 }
 
-fn resolve_task<C>(
-    tasks: &DenseSlotMap<DefaultKey, (String, Action<C>)>,
-    map: &HashMap<DefaultKey, Vec<DefaultKey>>,
-    root: &Vec<DefaultKey>,
-    deps: &Vec<DefaultKey>,
-) -> Result<Vec<DefaultKey>, Error> {
-    let deps: Vec<_> = deps
-        .iter()
-        .map(|m| {
-            let t = tasks.get(*m).unwrap();
-            if root.contains(m) {
-                return Err(Error::InvalidDepency(format!(
-                    "task '{}' cannot depend on self",
-                    t.0
-                )));
-            }
-            let mut roots = root.clone();
-            roots.push(*m);
-            resolve_task(tasks, map, &roots, map.get(m).unwrap())
-        })
-        .try_collect()?;
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Visiting,
+    Done,
+}
 
-    let mut deps = deps.into_iter().flatten().collect::<Vec<_>>();
+/// Depth first search from `key`, failing with the path of the first cycle found.
+fn check_cycles(
+    key: DefaultKey,
+    edges: &SecondaryMap<DefaultKey, Vec<DefaultKey>>,
+    marks: &mut SecondaryMap<DefaultKey, Mark>,
+    path: &mut Vec<DefaultKey>,
+) -> Result<(), Vec<DefaultKey>> {
+    match marks.get(key) {
+        Some(Mark::Done) => return Ok(()),
+        Some(Mark::Visiting) => {
+            let start = path.iter().position(|k| *k == key).unwrap();
+            let mut cycle = path[start..].to_vec();
+            cycle.push(key);
+            return Err(cycle);
+        }
+        None => {}
+    }
 
-    deps.push(*root.last().unwrap());
+    marks.insert(key, Mark::Visiting);
+    path.push(key);
+    for dep in edges[key].iter() {
+        check_cycles(*dep, edges, marks, path)?;
+    }
+    path.pop();
+    marks.insert(key, Mark::Done);
 
-    let mut seen = HashSet::new();
+    Ok(())
+}
 
-    Ok(deps
-        .into_iter()
-        .filter(|m| {
-            if seen.contains(m) {
-                false
-            } else {
-                seen.insert(*m);
-                true
+/// All dependencies of `key`, and `key` itself, in the order they should run.
+/// The graph must be acyclic.
+fn resolve_task(
+    key: DefaultKey,
+    edges: &SecondaryMap<DefaultKey, Vec<DefaultKey>>,
+    seen: &mut HashSet<DefaultKey>,
+    out: &mut Vec<DefaultKey>,
+) {
+    if !seen.insert(key) {
+        return;
+    }
+    for dep in edges[key].iter() {
+        resolve_task(*dep, edges, seen, out);
+    }
+    out.push(key);
+}
+
+/// Check the arguments `task` passes to `dep` against the parameters of both.
+fn check_dependency_args<C>(
+    task: &Task<C>,
+    dep: &Task<C>,
+    args: &[(String, ArgSource)],
+) -> Result<(), Error> {
+    let error = |message: String| Error::Argument {
+        task: task.name.clone(),
+        message,
+    };
+    for (name, source) in args {
+        let param = match dep.params.iter().find(|p| &p.name == name) {
+            Some(param) => param,
+            None => return Err(error(format!("'{}' has no parameter '{}'", dep.name, name))),
+        };
+        match source {
+            ArgSource::Fixed(value) if Value::parse(param.kind, value).is_none() => {
+                return Err(error(format!(
+                    "expected {} for '{}' of '{}', got '{}'",
+                    param.kind, name, dep.name, value
+                )))
             }
-        })
-        .collect())
+            ArgSource::Fixed(_) => {}
+            ArgSource::Forward(from) => match task.params.iter().find(|p| &p.name == from) {
+                Some(own) if own.kind == param.kind => {}
+                Some(own) => {
+                    return Err(error(format!(
+                        "cannot forward {} '{}' to {} '{}' of '{}'",
+                        own.kind, from, param.kind, name, dep.name
+                    )))
+                }
+                None => return Err(error(format!("unknown parameter '{}'", from))),
+            },
+        }
+    }
+    Ok(())
 }
 
-pub fn sort<C>(input: Vec<TaskDesc<C>>) -> Result<Band<C>, Error> {
+pub fn sort<C>(mut input: Vec<TaskDesc<C>>) -> Result<Band<C>, Error> {
+    namespace::add_aggregates(&mut input);
+
     let mut tasks = DenseSlotMap::default();
-    let mut byname_tmp = HashMap::default();
-    let mut pending = HashMap::<String, Vec<String>>::new();
-    let mut tmp = HashMap::<String, DefaultKey>::new();
     let mut byname = HashMap::new();
+    let mut pending = Vec::with_capacity(input.len());
 
     for task in input.into_iter() {
+        if byname.contains_key(&task.name) {
+            return Err(Error::DuplicateTask(task.name));
+        }
+        fingerprint::validate_globs(&task.inputs)?;
+        fingerprint::validate_globs(&task.outputs)?;
         let name = task.name;
-        let deps = task.dependencies;
-        let t = tasks.insert((name.clone(), task.action));
-        tmp.insert(name.clone(), t);
-        pending.insert(name, deps);
+        let key = tasks.insert(Task {
+            name: name.clone(),
+            description: task.description,
+            action: task.action,
+            params: task.params,
+            dependency_args: Vec::new(),
+            inputs: task.inputs,
+            outputs: task.outputs,
+            failure_policy: task.failure_policy,
+            retry: task.retry,
+            timeout: task.timeout,
+            conditions: task.conditions,
+        });
+        byname.insert(name, key);
+        pending.push((key, task.dependencies));
     }
 
-    for (task, deps) in pending.into_iter() {
-        let deps: Vec<_> = deps
-            .into_iter()
-            .map(|dep| match tmp.get(&dep) {
-                Some(s) => Ok(*s),
-                None => Err(Error::TaskNotFound(dep)),
-            })
-            .try_collect()?;
+    let mut edges = SecondaryMap::new();
+    for (key, deps) in pending.into_iter() {
+        let mut keys = Vec::with_capacity(deps.len());
+        for dep in deps {
+            let dep_key = match byname.get(&dep.name) {
+                Some(s) => *s,
+                None => {
+                    return Err(Error::UnknownDependency {
+                        task: tasks[key].name.clone(),
+                        dependency: dep.name,
+                    })
+                }
+            };
+            if !dep.args.is_empty() {
+                check_dependency_args(&tasks[key], &tasks[dep_key], &dep.args)?;
+                tasks[key].dependency_args.push((dep_key, dep.args));
+            }
+            keys.push(dep_key);
+        }
+        edges.insert(key, keys);
+    }
 
-        let root = *tmp.get(&task).unwrap();
-        byname_tmp.insert(root, deps);
-        byname.insert(task, root);
+    let mut marks = SecondaryMap::new();
+    for key in tasks.keys() {
+        if let Err(cycle) = check_cycles(key, &edges, &mut marks, &mut Vec::new()) {
+            return Err(Error::Cycle(
+                cycle.into_iter().map(|k| tasks[k].name.clone()).collect(),
+            ));
+        }
     }
 
     let mut dependencies = SecondaryMap::new();
-    for (root, deps) in byname_tmp.iter() {
-        let t = resolve_task(&tasks, &byname_tmp, &vec![*root], &deps)?;
-        dependencies.insert(*root, t);
+    for key in tasks.keys() {
+        let mut resolved = Vec::new();
+        resolve_task(key, &edges, &mut HashSet::new(), &mut resolved);
+        dependencies.insert(key, resolved);
     }
 
     Ok(Band {
         tasks,
         tasks_by_name: byname,
         dependencies,
+        edges,
+        concurrency: default_concurrency(),
+        failure_policy: FailurePolicy::default(),
+        store: None,
+        history: None,
+        reporters: Vec::new(),
+        hooks: Hooks::default(),
+        runs: AtomicU64::new(0),
+        #[cfg(feature = "watch")]
+        debounce: super::watch::DEFAULT_DEBOUNCE,
     })
 }
 
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
 pub struct BandBuilder<C, N = String> {
     tasks: Vec<TaskDesc<C>>,
+    overrides: Vec<TaskDesc<C>>,
+    wrappers: Vec<Wrapper<TaskContext<C>>>,
+    hooks: Hooks<C>,
+    concurrency: Option<usize>,
+    failure_policy: FailurePolicy,
+    manifest: Option<PathBuf>,
+    history: Option<PathBuf>,
+    reporters: Vec<Arc<dyn Reporter>>,
     _n: std::marker::PhantomData<N>,
 }
 
-impl<C, N> BandBuilder<C, N> {
+impl<C, N> BandBuilder<C, N>
+where
+    N: Into<String>,
+{
     pub fn add_task<A>(mut self, name: impl Into<N>, builder: TaskBuilder<A, C>) -> Self
     where
-        A: Service<C, Output = (C, ())> + 'static,
+        A: Service<TaskContext<C>, Output = (TaskContext<C>, ())> + Send + Sync + 'static,
         A::Future: Send,
         A::Error: Into<Error>,
         C: 'static,
     {
-        self.tasks.push(builder.build(name.into()));
+        self.tasks.push(builder.build(name.into().into()));
+        self
+    }
+
+    pub fn add_tasks(mut self, tasks: impl IntoIterator<Item = TaskDesc<C>>) -> Self {
+        self.tasks.extend(tasks);
+        self
+    }
+
+    /// Add tasks named `<name>:<task>`.
+    ///
+    /// Every namespace without a task of its own, whether added through this
+    /// or by naming tasks like `build:sass`, gets a task running all tasks in it.
+    ///
+    /// ```
+    /// # use band::{exec, Band, TaskBuilder};
+    /// let band = Band::<()>::new()
+    ///     .namespace("build", |ns| {
+    ///         ns.add_task("sass", TaskBuilder::new(exec("sass")))
+    ///             .add_task("js", TaskBuilder::new(exec("rollup")))
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(band.has_task("build:sass"));
+    /// assert!(band.has_task("build"));
+    /// ```
+    pub fn namespace<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(Namespace<C>) -> Namespace<C>,
+    {
+        let ns = f(Namespace::new(name.to_owned()));
+        self.tasks.extend(ns.tasks);
+        self
+    }
+
+    /// Add the tasks and reporters of `other`. Its other settings are ignored.
+    ///
+    /// Building fails with [`Error::DuplicateTask`] when both define a task with the
+    /// same name, unless it is replaced with [`BandBuilder::override_task`].
+    pub fn merge<M>(mut self, other: BandBuilder<C, M>) -> Self {
+        self.tasks.extend(other.tasks);
+        self.overrides.extend(other.overrides);
+        self.reporters.extend(other.reporters);
+        self
+    }
+
+    /// Like [`BandBuilder::merge`], with the tasks of `other` in the namespace `prefix`.
+    ///
+    /// Dependencies between the imported tasks are renamed along with them.
+    pub fn import<M>(mut self, prefix: &str, other: BandBuilder<C, M>) -> Self {
+        let names = other
+            .tasks
+            .iter()
+            .chain(other.overrides.iter())
+            .map(|t| t.name.clone())
+            .collect::<HashSet<_>>();
+        let prefixed = |tasks: Vec<TaskDesc<C>>| {
+            tasks
+                .into_iter()
+                .map(|t| t.prefixed(prefix, &names))
+                .collect::<Vec<_>>()
+        };
+        self.tasks.extend(prefixed(other.tasks));
+        self.overrides.extend(prefixed(other.overrides));
+        self.reporters.extend(other.reporters);
+        self
+    }
+
+    /// Replace the task `name`, which must be added to the band, for example by a merge.
+    pub fn override_task<A>(mut self, name: impl Into<N>, builder: TaskBuilder<A, C>) -> Self
+    where
+        A: Service<TaskContext<C>, Output = (TaskContext<C>, ())> + Send + Sync + 'static,
+        A::Future: Send,
+        A::Error: Into<Error>,
+        C: 'static,
+    {
+        self.overrides.push(builder.build(name.into().into()));
+        self
+    }
+
+    /// Wrap the action of every task in `middleware`, like logging, timing or locking.
+    ///
+    /// The middleware added last is the outermost. Middlewares added to a single task
+    /// with [`TaskBuilder::wrap`] run inside of the middlewares of the band.
+    pub fn wrap<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware<TaskContext<C>, SharedAction<TaskContext<C>>> + 'static,
+        M::Service: Service<TaskContext<C>, Output = (TaskContext<C>, ())> + Send + Sync + 'static,
+        <M::Service as Service<TaskContext<C>>>::Future: 'static,
+        <M::Service as Service<TaskContext<C>>>::Error: Into<Error>,
+        C: 'static,
+    {
+        self.wrappers.push(Box::new(move |action| {
+            Box::new(ActionBox(
+                middleware.wrap(SharedAction::new(action)),
+                PhantomData,
+            ))
+        }));
+        self
+    }
+
+    /// Run `action` before the tasks of every run. The run fails if it fails.
+    pub fn before_run<A>(mut self, action: A) -> Self
+    where
+        A: Service<C, Output = (C, ())> + Send + Sync + 'static,
+        A::Future: 'static,
+        A::Error: Into<Error>,
+        C: 'static,
+    {
+        self.hooks
+            .before
+            .push(Box::new(ActionBox(action, PhantomData)));
+        self
+    }
+
+    /// Run `action` after every run which got past the [`before_run`](Self::before_run)
+    /// actions, whether its tasks succeeded or not.
+    pub fn after_run<A>(mut self, action: A) -> Self
+    where
+        A: Service<C, Output = (C, ())> + Send + Sync + 'static,
+        A::Future: 'static,
+        A::Error: Into<Error>,
+        C: 'static,
+    {
+        self.hooks
+            .after
+            .push(Box::new(ActionBox(action, PhantomData)));
+        self
+    }
+
+    /// Call `hook` with the error of every failed run, including runs whose
+    /// [`before_run`](Self::before_run) actions failed.
+    pub fn on_failure(mut self, hook: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.hooks.on_failure.push(Box::new(hook));
+        self
+    }
+
+    /// Maximum number of tasks running at the same time.
+    /// Defaults to the available parallelism of the machine.
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = Some(limit);
+        self
+    }
+
+    /// How the run reacts to failing tasks without a failure policy of their own.
+    /// Defaults to [`FailurePolicy::FailFast`].
+    pub fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Persist fingerprints of task inputs and outputs in `path`.
+    ///
+    /// Tasks declaring inputs are skipped when neither their inputs nor their outputs
+    /// have changed since they last succeeded.
+    pub fn manifest(mut self, path: impl Into<PathBuf>) -> Self {
+        self.manifest = Some(path.into());
+        self
+    }
+
+    /// Record every run in `path`, keeping the last [`HISTORY_LIMIT`](crate::HISTORY_LIMIT).
+    ///
+    /// The recorded durations give [`Band::critical_path`] and the estimated
+    /// duration of a run in [`Event::RunStarted`]. Failing to save the history does
+    /// not fail the run, it is reported as an [`Event::Warning`].
+    pub fn history(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history = Some(path.into());
+        self
+    }
+
+    pub fn reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.push(Arc::new(reporter));
         self
     }
 
     pub fn build(self) -> Result<Band<C>, Error> {
-        sort(self.tasks)
+        let mut tasks = self.tasks;
+        for task in self.overrides {
+            match tasks.iter_mut().find(|t| t.name == task.name) {
+                Some(slot) => *slot = task,
+                None => return Err(Error::TaskNotFound(task.name)),
+            }
+        }
+        let wrappers = self.wrappers;
+        for task in &mut tasks {
+            task.action = task
+                .action
+                .take()
+                .map(|action| wrappers.iter().fold(action, |action, wrap| wrap(action)));
+        }
+
+        let mut band = sort(tasks)?;
+        band.hooks = self.hooks;
+        band.reporters = self.reporters;
+        band.failure_policy = self.failure_policy;
+        if let Some(limit) = self.concurrency {
+            band.set_concurrency(limit);
+        }
+        if let Some(path) = self.manifest {
+            band.store = Some(Store::open(path)?);
+        }
+        if let Some(path) = self.history {
+            let history = Arc::new(HistoryStore::open(path)?);
+            band.reporters.push(history.clone());
+            band.history = Some(history);
+        }
+        Ok(band)
     }
 }
 
+/// A task as seen from the outside of a band.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskInfo<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub params: &'a [Param],
+    pub dependencies: Vec<&'a str>,
+}
+
 pub struct Band<C> {
-    tasks: DenseSlotMap<DefaultKey, (String, Action<C>)>,
-    dependencies: SecondaryMap<DefaultKey, Vec<DefaultKey>>,
-    tasks_by_name: HashMap<String, DefaultKey>,
+    pub(crate) tasks: DenseSlotMap<DefaultKey, Task<C>>,
+    pub(crate) dependencies: SecondaryMap<DefaultKey, Vec<DefaultKey>>,
+    pub(crate) edges: SecondaryMap<DefaultKey, Vec<DefaultKey>>,
+    pub(crate) tasks_by_name: HashMap<String, DefaultKey>,
+    pub(crate) concurrency: usize,
+    pub(crate) failure_policy: FailurePolicy,
+    pub(crate) store: Option<Store>,
+    pub(crate) history: Option<Arc<HistoryStore>>,
+    pub(crate) reporters: Vec<Arc<dyn Reporter>>,
+    pub(crate) hooks: Hooks<C>,
+    /// Number of runs started, which is the id of the next run.
+    pub(crate) runs: AtomicU64,
+    #[cfg(feature = "watch")]
+    pub(crate) debounce: std::time::Duration,
 }
 
 impl<C> Band<C> {
     pub fn new() -> BandBuilder<C> {
         BandBuilder {
             tasks: Vec::new(),
+            overrides: Vec::new(),
+            wrappers: Vec::new(),
+            hooks: Hooks::default(),
+            concurrency: None,
+            failure_policy: FailurePolicy::default(),
+            manifest: None,
+            history: None,
+            reporters: Vec::new(),
             _n: PhantomData,
         }
     }
-    pub async fn run(&self, task: &str, ctx: C) -> Result<(), Error> {
+
+    pub fn set_concurrency(&mut self, limit: usize) {
+        self.concurrency = limit.max(1);
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn set_failure_policy(&mut self, policy: FailurePolicy) {
+        self.failure_policy = policy;
+    }
+
+    pub fn failure_policy(&self) -> FailurePolicy {
+        self.failure_policy
+    }
+
+    pub fn add_reporter(&mut self, reporter: impl Reporter + 'static) {
+        self.reporters.push(Arc::new(reporter));
+    }
+
+    /// Stream of the events of every following run.
+    pub fn subscribe(&mut self) -> impl futures_util::Stream<Item = Event> {
+        let (reporter, stream) = events::channel();
+        self.add_reporter(reporter);
+        stream
+    }
+
+    pub(crate) fn emit(&self, event: Event) {
+        for reporter in &self.reporters {
+            reporter.report(&event);
+        }
+    }
+
+    pub fn has_task(&self, name: &str) -> bool {
+        self.tasks_by_name.contains_key(name)
+    }
+
+    /// All tasks in the order they were added.
+    pub fn tasks(&self) -> impl Iterator<Item = TaskInfo<'_>> {
+        self.tasks.iter().map(move |(key, task)| TaskInfo {
+            name: &task.name,
+            description: task.description.as_deref(),
+            params: &task.params,
+            dependencies: self.edges[key]
+                .iter()
+                .map(|dep| self.tasks[*dep].name.as_str())
+                .collect(),
+        })
+    }
+
+    pub async fn run(&self, task: &str, ctx: C) -> Result<RunSummary, Error>
+    where
+        C: Clone,
+    {
         self.run_tasks(&[task], ctx).await
     }
 
-    pub async fn run_tasks(&self, tasks: &[&str], mut ctx: C) -> Result<(), Error> {
+    /// Run `tasks` and all of their dependencies.
+    ///
+    /// A task is started as soon as all of its dependencies are done, so independent
+    /// branches of the graph run concurrently, bounded by the band's concurrency limit.
+    /// Every task receives its own clone of `ctx`; state that must be visible
+    /// across tasks should live behind an `Arc` inside the context.
+    ///
+    /// When the band has a manifest, up-to-date tasks are skipped and the manifest
+    /// is saved when the run ends. Failures of optional tasks do not fail the run, they
+    /// are returned as the warnings of its summary.
+    pub async fn run_tasks(&self, tasks: &[&str], ctx: C) -> Result<RunSummary, Error>
+    where
+        C: Clone,
+    {
         let tasks = self.get_all_tasks(tasks)?;
-        for task in tasks {
-            let (c, _) = match self.tasks[task].1.call(ctx).await {
-                Ok(c) => c,
-                Err(err) => match err {
-                    Rejection::Err(err) => return Err(err),
-                    Rejection::Reject(_, Some(err)) => return Err(err),
-                    Rejection::Reject(_, None) => return Err(Error::Rejected),
-                },
-            };
-            ctx = c;
+        let args = self.resolve_args(&tasks, &[])?;
+        self.run_keys(&tasks, &args, &CancelHandle::new(), ctx)
+            .await
+    }
+
+    /// Like [`Band::run_tasks`], passing arguments to the tasks.
+    ///
+    /// Arguments are checked against the parameters of the tasks before anything runs.
+    /// A task reads its arguments with [`TaskContext::args`].
+    pub async fn run_with(&self, invocations: &[Invocation], ctx: C) -> Result<RunSummary, Error>
+    where
+        C: Clone,
+    {
+        self.run_invocations(invocations, &CancelHandle::new(), ctx)
+            .await
+    }
+
+    /// Start a run which can be cancelled from elsewhere through [`Running::cancel_handle`].
+    ///
+    /// The run makes progress while the returned future is awaited. It owns the band,
+    /// so it can be spawned on an executor.
+    /// On cancellation the running tasks are dropped, which kills the programs they
+    /// started, no further tasks are started and the run fails with [`Error::Cancelled`].
+    pub fn start(self: &Arc<Self>, invocations: &[Invocation], ctx: C) -> Running
+    where
+        C: Clone + Send + Sync + 'static,
+    {
+        let band = Arc::clone(self);
+        let handle = CancelHandle::new();
+        let invocations = invocations.to_vec();
+        let cancel = handle.clone();
+        let future = async move { band.run_invocations(&invocations, &cancel, ctx).await };
+        Running::new(future.boxed(), handle)
+    }
+
+    async fn run_invocations(
+        &self,
+        invocations: &[Invocation],
+        cancel: &CancelHandle,
+        ctx: C,
+    ) -> Result<RunSummary, Error>
+    where
+        C: Clone,
+    {
+        let names = invocations
+            .iter()
+            .map(|i| i.task.as_str())
+            .collect::<Vec<_>>();
+        let tasks = self.get_all_tasks(&names)?;
+        let args = self.resolve_args(&tasks, invocations)?;
+        self.run_keys(&tasks, &args, cancel, ctx).await
+    }
+
+    /// Resolve the arguments of every task in `tasks`, which must be in dependency order.
+    pub(crate) fn resolve_args(
+        &self,
+        tasks: &[DefaultKey],
+        invocations: &[Invocation],
+    ) -> Result<SecondaryMap<DefaultKey, Arc<Args>>, Error> {
+        let mut raw = SecondaryMap::<DefaultKey, Vec<(String, String)>>::new();
+        for invocation in invocations {
+            for key in self.select(&invocation.task)? {
+                raw.entry(key)
+                    .unwrap()
+                    .or_default()
+                    .extend(invocation.args.iter().cloned());
+            }
+        }
+
+        let mut resolved = SecondaryMap::new();
+        // Dependents come before their dependencies in reverse dependency order.
+        for key in tasks.iter().rev() {
+            let task = &self.tasks[*key];
+            let given = raw.remove(*key).unwrap_or_default();
+            let args = Args::validate(&task.name, &task.params, &given)?;
+
+            for (dep, dep_args) in &task.dependency_args {
+                let dep_raw = raw.entry(*dep).unwrap().or_default();
+                for (name, source) in dep_args {
+                    let value = match source {
+                        ArgSource::Fixed(value) => value.clone(),
+                        ArgSource::Forward(from) => match args.get(from) {
+                            Some(value) => value.to_string(),
+                            None => continue,
+                        },
+                    };
+                    dep_raw.push((name.clone(), value));
+                }
+            }
+
+            resolved.insert(*key, Arc::new(args));
         }
-        Ok(())
+
+        Ok(resolved)
     }
 
-    fn get_all_tasks(&self, tasks: &[&str]) -> Result<Vec<DefaultKey>, Error> {
+    pub(crate) async fn run_keys(
+        &self,
+        tasks: &[DefaultKey],
+        args: &SecondaryMap<DefaultKey, Arc<Args>>,
+        cancel: &CancelHandle,
+        ctx: C,
+    ) -> Result<RunSummary, Error>
+    where
+        C: Clone,
+    {
+        let start = Instant::now();
+        let run = RunId::new(self.runs.fetch_add(1, Ordering::Relaxed));
+        self.emit(Event::RunStarted {
+            run,
+            tasks: tasks.iter().map(|k| self.tasks[*k].name.clone()).collect(),
+            estimate: self.estimate(tasks),
+        });
+        if let Some(warning) = self
+            .history
+            .as_ref()
+            .and_then(|history| history.take_warning())
+        {
+            self.emit(Event::Warning { message: warning });
+        }
+
+        let ret = match self.hooks.before(&ctx).await {
+            Ok(()) => {
+                let mut ret = scheduler::run(self, run, tasks, args, cancel, ctx.clone()).await;
+                if let Some(store) = &self.store {
+                    ret = ret.and_then(|summary| store.save().map(|()| summary));
+                }
+                self.hooks.after(&ctx, ret).await
+            }
+            Err(err) => {
+                self.hooks.failed(&err);
+                Err(err)
+            }
+        };
+
+        self.emit(Event::RunFinished {
+            run,
+            duration: start.elapsed(),
+            success: ret.is_ok(),
+        });
+        if let Some(Err(err)) = self.history.as_ref().map(|history| history.save()) {
+            self.emit(Event::Warning {
+                message: format!("could not save the history: {}", err),
+            });
+        }
+        ret
+    }
+
+    /// The task named `name`, or the tasks matching it when it is a glob like `build:*`.
+    ///
+    /// A task whose name looks like a glob, like `test[unit]`, is selected by its name.
+    pub(crate) fn select(&self, name: &str) -> Result<Vec<DefaultKey>, Error> {
+        if let Some(key) = self.tasks_by_name.get(name) {
+            return Ok(vec![*key]);
+        }
+        if !namespace::is_pattern(name) {
+            return Err(Error::TaskNotFound(name.to_owned()));
+        }
+
+        let pattern =
+            glob::Pattern::new(name).map_err(|err| Error::Pattern(format!("{}: {}", name, err)))?;
+        let keys = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| pattern.matches(&task.name))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Err(Error::TaskNotFound(name.to_owned()));
+        }
+        Ok(keys)
+    }
+
+    /// Every task in dependency order.
+    pub(crate) fn all_tasks(&self) -> Vec<DefaultKey> {
+        let mut keys = Vec::with_capacity(self.tasks.len());
+        let mut seen = HashSet::with_capacity(self.tasks.len());
+        for key in self.tasks.keys() {
+            for dep in &self.dependencies[key] {
+                if seen.insert(*dep) {
+                    keys.push(*dep);
+                }
+            }
+        }
+        keys
+    }
+
+    pub(crate) fn get_all_tasks(&self, tasks: &[&str]) -> Result<Vec<DefaultKey>, Error> {
         let mut dependencies: Vec<DefaultKey> = Vec::new();
         for task in tasks {
-            let task = match self.tasks_by_name.get(*task) {
-                Some(s) => s,
-                None => return Err(Error::TaskNotFound((*task).to_owned())),
-            };
-
-            dependencies.extend(self.dependencies[*task].iter());
+            for key in self.select(task)? {
+                dependencies.extend(self.dependencies[key].iter());
+            }
         }
 
         let mut seen = HashSet::new();
@@ -220,7 +866,7 @@ impl<C> Band<C> {
 
         let deps = dependencies
             .iter()
-            .map(|m| &self.tasks.get(*m).unwrap().0)
+            .map(|m| &self.tasks.get(*m).unwrap().name)
             .collect();
 
         Some(deps)
@@ -229,16 +875,24 @@ impl<C> Band<C> {
 
 pub struct TaskBuilder<A, C>
 where
-    A: Service<C, Output = (C, ())>,
+    A: Service<TaskContext<C>, Output = (TaskContext<C>, ())>,
 {
     action: A,
-    dependencies: Vec<String>,
+    description: Option<String>,
+    params: Vec<Param>,
+    dependencies: Vec<Dependency>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    failure_policy: Option<FailurePolicy>,
+    retry: Attempts,
+    timeout: Option<Duration>,
+    conditions: Vec<Condition<C>>,
     _c: PhantomData<C>,
 }
 
 impl<A, C> TaskBuilder<A, C>
 where
-    A: Service<C, Output = (C, ())> + 'static,
+    A: Service<TaskContext<C>, Output = (TaskContext<C>, ())> + Send + Sync + 'static,
     A::Future: Send,
     A::Error: Into<Error>,
     C: 'static,
@@ -246,56 +900,171 @@ where
     pub fn new(action: A) -> TaskBuilder<A, C> {
         TaskBuilder {
             action,
+            description: None,
+            params: Vec::new(),
             dependencies: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            failure_policy: None,
+            retry: Attempts::new(0),
+            timeout: None,
+            conditions: Vec::new(),
             _c: PhantomData,
         }
     }
 
+    pub fn description(mut self, description: impl ToString) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Declare a parameter. Arguments are validated against the parameters of a task
+    /// before a run starts.
+    pub fn param(mut self, param: Param) -> Self {
+        self.params.push(param);
+        self
+    }
+
     pub fn add_dependency(mut self, name: impl ToString) -> Self {
-        self.dependencies.push(name.to_string());
+        self.dependencies.push(Dependency::new(name));
+        self
+    }
+
+    /// Add a dependency which is passed fixed or forwarded arguments.
+    pub fn add_dependency_with(mut self, dependency: Dependency) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Glob of files the task reads. Tasks with inputs take part in incremental runs.
+    pub fn input(mut self, glob: impl ToString) -> Self {
+        self.inputs.push(glob.to_string());
+        self
+    }
+
+    /// Glob of files the task produces.
+    pub fn output(mut self, glob: impl ToString) -> Self {
+        self.outputs.push(glob.to_string());
+        self
+    }
+
+    /// Wrap the action of the task in `middleware`.
+    pub fn wrap<M>(self, middleware: M) -> TaskBuilder<M::Service, C>
+    where
+        M: Middleware<TaskContext<C>, A>,
+        M::Service: Service<TaskContext<C>, Output = (TaskContext<C>, ())>,
+    {
+        TaskBuilder {
+            action: middleware.wrap(self.action),
+            description: self.description,
+            params: self.params,
+            dependencies: self.dependencies,
+            inputs: self.inputs,
+            outputs: self.outputs,
+            failure_policy: self.failure_policy,
+            retry: self.retry,
+            timeout: self.timeout,
+            conditions: self.conditions,
+            _c: PhantomData,
+        }
+    }
+
+    /// Overrides the failure policy of the band for this task.
+    pub fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = Some(policy);
+        self
+    }
+
+    /// The task may fail without failing the run. Tasks depending on it still run.
+    pub fn optional(self) -> Self {
+        self.failure_policy(FailurePolicy::Optional)
+    }
+
+    /// Retry the task up to `retries` times, waiting `backoff` between attempts.
+    pub fn retry(mut self, retries: u32, backoff: Backoff) -> Self {
+        self.retry = Attempts::new(retries).backoff(backoff);
+        self
+    }
+
+    /// Fail every attempt of the task running longer than `timeout` with [`Error::Timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
         self
     }
 
-    fn build<N>(self, name: N) -> TaskDesc<C> {
+    /// Only run the task when `predicate` holds for its context.
+    ///
+    /// Predicates are checked when the task is about to start. A task skipped
+    /// because of them is reported as [`SkipReason::Condition`](crate::SkipReason::Condition)
+    /// and counts as done for the tasks depending on it.
+    ///
+    /// ```
+    /// # use band::{conditions, exec, Band, TaskBuilder};
+    /// # use service::Service;
+    /// let band = Band::<()>::new()
+    ///     .add_task(
+    ///         "deploy",
+    ///         TaskBuilder::new(exec("./deploy.sh"))
+    ///             .only_if(conditions::env_set("DEPLOY_TOKEN"))
+    ///             // Skip when the working tree is clean.
+    ///             .skip_if(|ctx| async move {
+    ///                 exec("git").args(["diff", "--quiet"]).call(ctx).await.is_ok()
+    ///             }),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn only_if<F, U>(mut self, predicate: F) -> Self
+    where
+        F: Fn(TaskContext<C>) -> U + Send + Sync + 'static,
+        U: std::future::Future<Output = bool> + Send + 'static,
+    {
+        self.conditions.push(conditions::condition(predicate, true));
+        self
+    }
+
+    /// Skip the task when `predicate` holds for its context. See [`TaskBuilder::only_if`].
+    pub fn skip_if<F, U>(mut self, predicate: F) -> Self
+    where
+        F: Fn(TaskContext<C>) -> U + Send + Sync + 'static,
+        U: std::future::Future<Output = bool> + Send + 'static,
+    {
+        self.conditions
+            .push(conditions::condition(predicate, false));
+        self
+    }
+
+    pub(crate) fn build(self, name: String) -> TaskDesc<C> {
         TaskDesc {
             name,
-            action: Box::new(ActionBox::<A, C>(self.action, PhantomData)),
+            description: self.description,
+            action: Some(Box::new(ActionBox::<A, TaskContext<C>>(
+                self.action,
+                PhantomData,
+            ))),
+            params: self.params,
             dependencies: self.dependencies,
+            inputs: self.inputs,
+            outputs: self.outputs,
+            failure_policy: self.failure_policy,
+            retry: self.retry,
+            timeout: self.timeout,
+            conditions: self.conditions,
         }
     }
 }
 
-pub enum Dependency<T> {
-    Single(T),
-    Parallel(Vec<T>),
-}
-
-// impl<T> Dependency<T> {
-//     fn run<C: Clone>(
-//         &self,
-//         tasks: &DenseSlotMap<DefaultKey, (String, Action<C>)>,
-//         ctx: C,
-//     ) -> Result<impl Future + Send + 'static, Error> {
-//         let v = match self {
-//             Dependency::Single(s) => {
-//                 let action = match tasks.get(s){
-//                     Some(s) => s,
-//                     None => return Err(Error::Rejected)
-//                 };
-//                 action.1.run(ctx)
-//             },
-//             Dependency::Parallel(p) => {
-//             }
-//         }
-//     }
-// }
-
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::testing::{flaky, task, Log};
+    use futures::executor::block_on;
     use futures_util::future;
     use service::service;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Poll;
 
     struct Test;
     impl<C: Send> Service<C> for Test {
@@ -320,8 +1089,8 @@ mod test {
             )
             .add_task(
                 "build",
-                TaskBuilder::new(service!(|_| async move {
-                    Result::<_, Rejection<_, Error>>::Ok(((), ()))
+                TaskBuilder::new(service!(|ctx| async move {
+                    Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
                 }))
                 // .add_dependency("clean")
                 .add_dependency("build:sass"),
@@ -340,6 +1109,259 @@ mod test {
             band.get_tasks(&["clean", "build", "build:sass"])
         );
 
-        band.run("build", ());
+        block_on(band.run("build", ())).unwrap();
+    }
+
+    #[test]
+    fn test_cycle() {
+        let err = Band::<()>::new()
+            .add_task("main", TaskBuilder::new(Test).add_dependency("a"))
+            .add_task("a", TaskBuilder::new(Test).add_dependency("b"))
+            .add_task("b", TaskBuilder::new(Test).add_dependency("c"))
+            .add_task("c", TaskBuilder::new(Test).add_dependency("a"))
+            .build()
+            .err()
+            .unwrap();
+
+        match err {
+            Error::Cycle(cycle) => assert_eq!(cycle, vec!["a", "b", "c", "a"]),
+            err => panic!("expected cycle, got: {}", err),
+        }
+
+        let err = Band::<()>::new()
+            .add_task("a", TaskBuilder::new(Test).add_dependency("a"))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "dependency cycle: a -> a");
+
+        let err = Band::<()>::new()
+            .add_task("a", TaskBuilder::new(Test).add_dependency("missing"))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "task 'a' depends on unknown task 'missing'"
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Counter {
+        running: Arc<AtomicUsize>,
+        max: Arc<AtomicUsize>,
+    }
+
+    fn counted(
+    ) -> impl Service<TaskContext<Counter>, Output = (TaskContext<Counter>, ()), Error = Error>
+    {
+        service!(|ctx: TaskContext<Counter>| async move {
+            let now = ctx.running.fetch_add(1, Ordering::SeqCst) + 1;
+            ctx.max.fetch_max(now, Ordering::SeqCst);
+            // Yield a couple of times so sibling tasks get a chance to start.
+            let mut polls = 0;
+            future::poll_fn(|cx| {
+                polls += 1;
+                if polls > 3 {
+                    Poll::Ready(())
+                } else {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+            .await;
+            ctx.running.fetch_sub(1, Ordering::SeqCst);
+            Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+        })
+    }
+
+    fn parallel_band(concurrency: usize) -> Band<Counter> {
+        Band::new()
+            .add_task(
+                "all",
+                TaskBuilder::new(counted())
+                    .add_dependency("a")
+                    .add_dependency("b")
+                    .add_dependency("c"),
+            )
+            .add_task("a", TaskBuilder::new(counted()))
+            .add_task("b", TaskBuilder::new(counted()))
+            .add_task("c", TaskBuilder::new(counted()))
+            .concurrency(concurrency)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parallel() {
+        let ctx = Counter::default();
+        block_on(parallel_band(3).run("all", ctx.clone())).unwrap();
+        assert_eq!(ctx.max.load(Ordering::SeqCst), 3);
+
+        let ctx = Counter::default();
+        block_on(parallel_band(1).run("all", ctx.clone())).unwrap();
+        assert_eq!(ctx.max.load(Ordering::SeqCst), 1);
+    }
+
+    fn failing_band(policy: FailurePolicy) -> Band<Log> {
+        Band::new()
+            .add_task(
+                "all",
+                TaskBuilder::new(task("all"))
+                    .add_dependency("after")
+                    .add_dependency("other"),
+            )
+            .add_task(
+                "after",
+                TaskBuilder::new(task("after")).add_dependency("fail"),
+            )
+            .add_task("fail", TaskBuilder::new(task("fail")))
+            .add_task("other", TaskBuilder::new(task("other")))
+            .failure_policy(policy)
+            .concurrency(1)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_failure_policy() {
+        let log = Log::default();
+        let err = block_on(failing_band(FailurePolicy::FailFast).run("all", log.clone()));
+        assert!(matches!(err, Err(Error::Rejected)));
+        assert_eq!(*log.lock().unwrap(), vec!["fail"]);
+
+        let log = Log::default();
+        let err =
+            block_on(failing_band(FailurePolicy::KeepGoing).run("all", log.clone())).unwrap_err();
+        assert_eq!(err.to_string(), "1 task(s) failed\n  fail: task rejected");
+        assert_eq!(*log.lock().unwrap(), vec!["fail", "other"]);
+
+        let log = Log::default();
+        let summary =
+            block_on(failing_band(FailurePolicy::Optional).run("all", log.clone())).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["fail", "other", "after", "all"]);
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(summary.warnings[0].task, "fail");
+
+        let log = Log::default();
+        let band = Band::new()
+            .add_task(
+                "flaky",
+                TaskBuilder::new(flaky("flaky", 2)).retry(2, Backoff::None),
+            )
+            .build()
+            .unwrap();
+        block_on(band.run("flaky", log.clone())).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    type Seen = Arc<std::sync::Mutex<Vec<(&'static str, Arc<Args>)>>>;
+
+    fn record(
+        name: &'static str,
+    ) -> impl Service<TaskContext<Seen>, Output = (TaskContext<Seen>, ()), Error = Error> {
+        service!(move |ctx: TaskContext<Seen>| async move {
+            ctx.lock().unwrap().push((name, ctx.args().clone()));
+            Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+        })
+    }
+
+    #[test]
+    fn test_args() {
+        let band = Band::new()
+            .add_task(
+                "build",
+                TaskBuilder::new(record("build"))
+                    .param(Param::flag("release"))
+                    .add_dependency_with(
+                        Dependency::new("build:sass")
+                            .arg("minify", true)
+                            .forward("release"),
+                    ),
+            )
+            .add_task(
+                "build:sass",
+                TaskBuilder::new(record("build:sass"))
+                    .param(Param::flag("minify"))
+                    .param(Param::flag("release")),
+            )
+            .build()
+            .unwrap();
+
+        let seen = Seen::default();
+        block_on(band.run_with(
+            &[Invocation::new("build").arg("release", true)],
+            seen.clone(),
+        ))
+        .unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].0, "build:sass");
+        assert!(seen[0].1.get_bool("minify"));
+        assert!(seen[0].1.get_bool("release"));
+        assert!(seen[1].1.get_bool("release"));
+
+        let err = block_on(band.run_with(
+            &[Invocation::new("build").arg("target", "x")],
+            Seen::default(),
+        ))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "task 'build': unknown parameter 'target'");
+
+        let err = Band::<()>::new()
+            .add_task(
+                "a",
+                TaskBuilder::new(Test).add_dependency_with(Dependency::new("b").forward("release")),
+            )
+            .add_task("b", TaskBuilder::new(Test).param(Param::flag("release")))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "task 'a': unknown parameter 'release'");
+    }
+
+    #[test]
+    fn test_compose() {
+        let library = || {
+            Band::new()
+                .add_task("clean", TaskBuilder::new(record("lib clean")))
+                .add_task(
+                    "build",
+                    TaskBuilder::new(record("lib build")).add_dependency("clean"),
+                )
+        };
+
+        let band = Band::new()
+            .import("lib", library())
+            .add_task(
+                "build",
+                TaskBuilder::new(record("build")).add_dependency("lib:build"),
+            )
+            .override_task("lib:clean", TaskBuilder::new(record("clean")))
+            .build()
+            .unwrap();
+        assert_eq!(
+            band.get_tasks(&["build"]).unwrap(),
+            vec!["lib:clean", "lib:build", "build"]
+        );
+        let seen = Seen::default();
+        block_on(band.run("build", seen.clone())).unwrap();
+        let names = seen.lock().unwrap().iter().map(|s| s.0).collect::<Vec<_>>();
+        assert_eq!(names, vec!["clean", "lib build", "build"]);
+
+        let err = Band::new()
+            .merge(library())
+            .add_task("clean", TaskBuilder::new(record("clean")))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "task 'clean' is defined more than once");
+
+        let err = Band::new()
+            .merge(library())
+            .override_task("missing", TaskBuilder::new(record("missing")))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "task 'missing' not found");
     }
 }
@@ -0,0 +1,45 @@
+use band::{
+    cli::{self, Options},
+    file::TaskFile,
+};
+use std::path::PathBuf;
+use std::process;
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("band: {}\n\n{}", err, cli::USAGE);
+            process::exit(cli::USAGE_EXIT_CODE);
+        }
+    };
+
+    if options.command == cli::Command::Help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let path = options
+        .file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(cli::DEFAULT_FILE));
+
+    let band = TaskFile::load(&path).and_then(|mut file| {
+        for (name, value) in &options.variables {
+            file.set(name.as_str(), value.as_str());
+        }
+        let root = file.root().map(PathBuf::from).unwrap_or_default();
+        file.into_builder::<()>()
+            .manifest(root.join(cli::MANIFEST_FILE))
+            .history(root.join(cli::HISTORY_FILE))
+            .build()
+    });
+
+    match band {
+        Ok(band) => process::exit(cli::run(band, &options, ())),
+        Err(err) => {
+            eprintln!("band: {}: {}", path.display(), err);
+            process::exit(err.exit_code());
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use super::{Error, RunSummary};
+use futures_util::future::{self, BoxFuture};
+use futures_util::task::AtomicWaker;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+/// Cancels a run started with [`Band::start`](crate::Band::start).
+///
+/// The handle can be cloned and moved to other threads.
+#[derive(Clone, Default)]
+pub struct CancelHandle(Arc<Inner>);
+
+impl CancelHandle {
+    pub fn new() -> CancelHandle {
+        CancelHandle::default()
+    }
+
+    /// Stop the run. Running tasks are dropped and no further tasks are started.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.waker.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the handle is cancelled. Only one task may wait at a time.
+    pub(crate) fn cancelled(&self) -> impl Future<Output = ()> + Unpin + '_ {
+        future::poll_fn(move |cx| {
+            if self.is_cancelled() {
+                return Poll::Ready(());
+            }
+            self.0.waker.register(cx.waker());
+            if self.is_cancelled() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// A run of a band which can be cancelled while it is awaited.
+pub struct Running {
+    future: BoxFuture<'static, Result<RunSummary, Error>>,
+    handle: CancelHandle,
+}
+
+impl Running {
+    pub(crate) fn new(
+        future: BoxFuture<'static, Result<RunSummary, Error>>,
+        handle: CancelHandle,
+    ) -> Self {
+        Running { future, handle }
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.handle.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.handle.cancel()
+    }
+}
+
+impl Future for Running {
+    type Output = Result<RunSummary, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{exec, Band, Event, TaskBuilder, TaskContext};
+    use service::{service, Rejection};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn slow(
+    ) -> impl service::Service<TaskContext<()>, Output = (TaskContext<()>, ()), Error = Error> {
+        service!(|ctx: TaskContext<()>| async move {
+            runtime::sleep(Duration::from_secs(10)).await;
+            Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+        })
+    }
+
+    #[test]
+    fn test_cancel() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let band = Band::new()
+            .add_task("slow", TaskBuilder::new(slow()))
+            .add_task("after", TaskBuilder::new(slow()).add_dependency("slow"))
+            .reporter(move |event: &Event| recorded.lock().unwrap().push(event.kind()))
+            .build()
+            .map(Arc::new)
+            .unwrap();
+
+        let running = band.start(&["after".into()], ());
+        let handle = running.cancel_handle();
+        let run = std::thread::spawn(move || runtime::block_on(running));
+        std::thread::sleep(Duration::from_millis(20));
+        handle.cancel();
+        let ret = run.join().unwrap();
+        assert!(matches!(ret, Err(Error::Cancelled)));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "run_started",
+                "task_queued",
+                "task_queued",
+                "task_started",
+                "task_cancelled",
+                "run_finished"
+            ]
+        );
+
+        // Failing fast drops the running tasks.
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let band = Band::new()
+            .add_task("slow", TaskBuilder::new(slow()))
+            .add_task(
+                "fail",
+                TaskBuilder::new(service!(|ctx: TaskContext<()>| async move {
+                    runtime::sleep(Duration::from_millis(10)).await;
+                    Result::<_, Rejection<_, Error>>::Err(Rejection::Reject(ctx, None))
+                })),
+            )
+            .reporter(move |event: &Event| recorded.lock().unwrap().push(event.kind()))
+            .concurrency(2)
+            .build()
+            .unwrap();
+        let ret = runtime::block_on(band.run_with(&["slow".into(), "fail".into()], ()));
+        assert!(matches!(ret, Err(Error::Rejected)));
+        assert_eq!(
+            events.lock().unwrap()[4..],
+            [
+                "task_started",
+                "task_failed",
+                "task_cancelled",
+                "run_finished"
+            ]
+        );
+
+        let band = Band::new()
+            .add_task(
+                "slow",
+                TaskBuilder::new(slow()).timeout(Duration::from_millis(10)),
+            )
+            .build()
+            .unwrap();
+        let err = runtime::block_on(band.run("slow", ())).unwrap_err();
+        assert_eq!(err.to_string(), "timed out after 10ms");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_kill_children() {
+        let dir = std::env::temp_dir().join(format!("band-kill-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("pid");
+        let band = |timeout: Option<Duration>| {
+            let sleep = exec("sh")
+                .arg("-c")
+                .arg(format!("echo $$ > {}; exec sleep 10", pid_file.display()));
+            let task = match timeout {
+                Some(timeout) => TaskBuilder::new(sleep).timeout(timeout),
+                None => TaskBuilder::new(sleep),
+            };
+            Arc::new(Band::new().add_task("sleep", task).build().unwrap())
+        };
+        // Waits for the program to write its pid, and checks whether it is still running.
+        let alive = || loop {
+            match std::fs::read_to_string(&pid_file) {
+                Ok(pid) if pid.ends_with('\n') => {
+                    let status = std::process::Command::new("kill")
+                        .args(["-0", pid.trim()])
+                        .stderr(std::process::Stdio::null())
+                        .status()
+                        .unwrap();
+                    break status.success();
+                }
+                _ => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        let err =
+            runtime::block_on(band(Some(Duration::from_millis(200))).run("sleep", ())).unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)));
+        assert!(!alive());
+        std::fs::remove_file(&pid_file).unwrap();
+
+        let running = band(None).start(&["sleep".into()], ());
+        let handle = running.cancel_handle();
+        let run = std::thread::spawn(move || runtime::block_on(running));
+        assert!(alive());
+        handle.cancel();
+        assert!(matches!(run.join().unwrap(), Err(Error::Cancelled)));
+        assert!(!alive());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,626 @@
+use super::events::format_duration;
+use super::{
+    Band, BandBuilder, ConsoleReporter, Error, FailurePolicy, Invocation, JsonReporter, Param,
+    ParamKind,
+};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Task file loaded by the `band` binary when no `--file` is given.
+pub const DEFAULT_FILE: &str = "task.task";
+
+/// Fingerprint manifest used by the `band` binary, relative to the task file.
+pub const MANIFEST_FILE: &str = ".band/manifest.json";
+
+/// Run history used by the `band` binary, relative to the task file.
+pub const HISTORY_FILE: &str = ".band/history.json";
+
+pub const USAGE: &str = "Usage: band [OPTIONS] [COMMAND] [TASKS]...
+
+Commands:
+    list            List all tasks with their descriptions
+    order TASKS     Print the order TASKS and their dependencies run in
+    graph [TASKS]   Print the dependency graph of TASKS, or of all tasks
+    stats [TASKS]   Print the slowest tasks and the critical path of TASKS
+    run TASKS       Run TASKS and their dependencies (default)
+
+Arguments of a task follow its name, like `band build --release --target x`.
+Values looking like a variable are given as `--name=a=b`.
+TASKS may be globs like `build:*`.
+
+Options:
+    -f, --file PATH     Task file to load (default: task.task)
+    -j, --jobs N        Run at most N tasks at the same time
+    -k, --keep-going    Keep running tasks not depending on a failed task
+    -n, --dry-run       Print what would run without running anything
+    -w, --watch         Run TASKS again when their inputs change
+    -r, --reporter NAME Progress output: pretty (default), json or none
+    --format NAME       Graph output: dot (default) or json
+    -h, --help          Print this message
+    NAME=VALUE          Override a variable in the task file";
+
+/// Exit code used when the command line could not be parsed.
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    List,
+    Order(Vec<String>),
+    Graph(Vec<String>),
+    Stats(Vec<String>),
+    /// Run the tasks given by [`Options::invocations`].
+    Run,
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReporterKind {
+    Pretty,
+    Json,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub file: Option<PathBuf>,
+    pub jobs: Option<usize>,
+    pub keep_going: bool,
+    pub dry_run: bool,
+    pub watch: bool,
+    pub reporter: ReporterKind,
+    pub format: GraphFormat,
+    pub variables: Vec<(String, String)>,
+    /// Tasks to run followed by their arguments, as given.
+    words: Vec<String>,
+}
+
+/// Long options of `band`, which task parameters must not shadow.
+const OPTIONS: &[&str] = &[
+    "file",
+    "jobs",
+    "keep-going",
+    "dry-run",
+    "watch",
+    "reporter",
+    "format",
+    "help",
+];
+
+fn is_command(word: &str) -> bool {
+    matches!(word, "list" | "order" | "graph" | "stats" | "run")
+}
+
+impl Options {
+    /// Parse command line arguments, not including the program name.
+    pub fn parse<I, S>(args: I) -> Result<Options, UsageError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        let mut file = None;
+        let mut jobs = None;
+        let mut keep_going = false;
+        let mut dry_run = false;
+        let mut watch = false;
+        let mut reporter = ReporterKind::Pretty;
+        let mut format = GraphFormat::Dot;
+        let mut help = false;
+        let mut variables = Vec::new();
+        let mut words = Vec::<String>::new();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.find('=') {
+                Some(idx) if arg.starts_with("--") => {
+                    (arg[..idx].to_owned(), Some(arg[idx + 1..].to_owned()))
+                }
+                _ => (arg.clone(), None),
+            };
+
+            let mut value = |name: &str| match inline.clone().or_else(|| args.next()) {
+                Some(value) => Ok(value),
+                None => Err(UsageError(format!("missing value for {}", name))),
+            };
+
+            match flag.as_str() {
+                "-f" | "--file" => file = Some(PathBuf::from(value(&flag)?)),
+                "-j" | "--jobs" => {
+                    let n = value(&flag)?;
+                    jobs = match n.parse::<usize>() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => return Err(UsageError(format!("invalid number of jobs: {}", n))),
+                    };
+                }
+                "-k" | "--keep-going" => keep_going = true,
+                "-n" | "--dry-run" => dry_run = true,
+                "-w" | "--watch" => watch = true,
+                "-r" | "--reporter" => {
+                    reporter = match value(&flag)?.as_str() {
+                        "pretty" => ReporterKind::Pretty,
+                        "json" => ReporterKind::Json,
+                        "none" => ReporterKind::None,
+                        name => return Err(UsageError(format!("unknown reporter: {}", name))),
+                    }
+                }
+                "--format" => {
+                    format = match value(&flag)?.as_str() {
+                        "dot" => GraphFormat::Dot,
+                        "json" => GraphFormat::Json,
+                        name => return Err(UsageError(format!("unknown format: {}", name))),
+                    }
+                }
+                "-h" | "--help" => help = true,
+                // Arguments of tasks are checked by `invocations`, which knows their parameters.
+                s if s.starts_with('-') && words.iter().any(|w| !is_command(w)) => words.push(arg),
+                s if s.starts_with('-') => {
+                    return Err(UsageError(format!("unknown option: {}", s)))
+                }
+                _ => match arg.find('=') {
+                    Some(idx) => {
+                        variables.push((arg[..idx].to_owned(), arg[idx + 1..].to_owned()))
+                    }
+                    None => words.push(arg),
+                },
+            }
+        }
+
+        let tasks = |words: &[String]| {
+            words
+                .iter()
+                .filter(|w| !w.starts_with('-'))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let command = match words.first().map(|w| w.as_str()) {
+            _ if help => Command::Help,
+            Some("list") => Command::List,
+            Some("order") => Command::Order(tasks(&words[1..])),
+            Some("graph") => Command::Graph(tasks(&words[1..])),
+            Some("stats") => Command::Stats(tasks(&words[1..])),
+            Some("run") => {
+                words.remove(0);
+                Command::Run
+            }
+            _ => Command::Run,
+        };
+        if command != Command::Run {
+            words.clear();
+        }
+
+        match &command {
+            Command::Order(tasks) if tasks.is_empty() => {
+                Err(UsageError("no tasks given".to_owned()))
+            }
+            Command::Run if words.is_empty() => Err(UsageError("no tasks given".to_owned())),
+            _ => Ok(Options {
+                command,
+                file,
+                jobs,
+                keep_going,
+                dry_run,
+                watch,
+                reporter,
+                format,
+                variables,
+                words,
+            }),
+        }
+    }
+
+    /// Tasks of [`Command::Run`], with their arguments checked against the parameters
+    /// of the tasks.
+    ///
+    /// `--name value` sets `name` when it is a parameter of the task which is not a flag,
+    /// taking the next word unless it starts with `--`. Tasks with a parameter named
+    /// like an option of `band` are rejected, as the option would take its arguments.
+    pub fn invocations<C>(&self, band: &Band<C>) -> Result<Vec<Invocation>, Error> {
+        let mut invocations = Vec::<Invocation>::new();
+        let mut params: &[Param] = &[];
+        let mut words = self.words.iter().peekable();
+        while let Some(word) = words.next() {
+            let invocation = match invocations.last_mut() {
+                Some(invocation) if word.starts_with('-') => invocation,
+                _ => {
+                    params = band
+                        .tasks()
+                        .find(|t| t.name == *word)
+                        .map_or(&[], |t| t.params);
+                    if let Some(param) = params.iter().find(|p| OPTIONS.contains(&p.name.as_str()))
+                    {
+                        return Err(Error::Argument {
+                            task: word.clone(),
+                            message: format!(
+                                "parameter '{}' is shadowed by the option --{} of band",
+                                param.name, param.name
+                            ),
+                        });
+                    }
+                    invocations.push(Invocation::new(word.as_str()));
+                    continue;
+                }
+            };
+            let error = |message: String| Error::Argument {
+                task: invocation.task.clone(),
+                message,
+            };
+
+            let arg = match word.strip_prefix("--") {
+                Some(arg) => arg,
+                None => return Err(error(format!("unexpected argument '{}'", word))),
+            };
+            let (name, value) = match arg.find('=') {
+                Some(idx) => (&arg[..idx], Some(arg[idx + 1..].to_owned())),
+                None => (arg, None),
+            };
+            let takes_value = params
+                .iter()
+                .any(|p| p.name == name && p.kind != ParamKind::Bool);
+            let value = match value {
+                Some(value) => value,
+                None if !takes_value => "true".to_owned(),
+                None => match words.next_if(|w| !w.starts_with("--")) {
+                    Some(value) => value.clone(),
+                    None => return Err(error(format!("missing value for '{}'", name))),
+                },
+            };
+            invocation.args.push((name.to_owned(), value));
+        }
+        Ok(invocations)
+    }
+}
+
+fn param_usage(param: &Param) -> String {
+    match &param.default {
+        Some(default) => format!("--{}={}", param.name, default),
+        None => format!("--{}=<{}>", param.name, param.kind),
+    }
+}
+
+fn list<C>(band: &Band<C>, out: &mut impl Write) -> io::Result<()> {
+    let width = band
+        .tasks()
+        .flat_map(|t| {
+            let params = t.params.iter().map(|p| param_usage(p).len() + 2);
+            std::iter::once(t.name.len()).chain(params)
+        })
+        .max()
+        .unwrap_or(0);
+    for task in band.tasks() {
+        match task.description {
+            Some(desc) => writeln!(out, "{:width$}  {}", task.name, desc, width = width)?,
+            None => writeln!(out, "{}", task.name)?,
+        }
+        for param in task.params {
+            let usage = format!("  {}", param_usage(param));
+            match &param.description {
+                Some(desc) => writeln!(out, "{:width$}  {}", usage, desc, width = width)?,
+                None => writeln!(out, "{}", usage)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn order<C>(band: &Band<C>, tasks: &[&str], out: &mut impl Write) -> Result<(), Error> {
+    for (idx, task) in band.graph_for(tasks)?.tasks.iter().enumerate() {
+        writeln!(out, "{:>3}. {}", idx + 1, task.name)?;
+    }
+    Ok(())
+}
+
+fn graph<C>(
+    band: &Band<C>,
+    tasks: &[&str],
+    format: GraphFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let graph = if tasks.is_empty() {
+        band.graph()
+    } else {
+        band.graph_for(tasks)?
+    };
+    match format {
+        GraphFormat::Dot => write!(out, "{}", graph.to_dot())?,
+        GraphFormat::Json => writeln!(out, "{}", graph.to_json())?,
+    }
+    Ok(())
+}
+
+/// Number of tasks listed by `band stats`.
+const SLOWEST_TASKS: usize = 10;
+
+fn stats<C>(band: &Band<C>, tasks: &[&str], out: &mut impl Write) -> Result<(), Error> {
+    let history = match band.history() {
+        Some(history) if !history.runs().is_empty() => history,
+        _ => {
+            writeln!(out, "no runs recorded")?;
+            return Ok(());
+        }
+    };
+
+    let stats = band.stats(tasks)?;
+    let width = stats.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    writeln!(
+        out,
+        "Slowest tasks of the last {} runs:",
+        history.runs().len()
+    )?;
+    for task in stats.iter().take(SLOWEST_TASKS) {
+        writeln!(
+            out,
+            "  {:width$}  {:>8}  max {:>8}  {} runs, {} failed, {} up to date",
+            task.name,
+            format_duration(task.mean),
+            format_duration(task.max),
+            task.runs,
+            task.failures,
+            task.cache_hits,
+            width = width
+        )?;
+    }
+
+    let path = band.critical_path(tasks)?;
+    writeln!(out, "Critical path, {}:", format_duration(path.duration))?;
+    writeln!(out, "  {}", path.tasks.join(" -> "))?;
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+async fn watch<C>(band: &Band<C>, tasks: &[Invocation], ctx: C) -> Result<(), Error>
+where
+    C: Clone,
+{
+    use futures_util::StreamExt;
+    let mut runs = Box::pin(band.watch_with(tasks, ctx)?);
+    while let Some(ret) = runs.next().await {
+        if let Err(err) = ret {
+            eprintln!("band: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "watch"))]
+async fn watch<C>(_band: &Band<C>, _tasks: &[Invocation], _ctx: C) -> Result<(), Error> {
+    Err(Error::External(Box::new(io::Error::other(
+        "band was built without the watch feature",
+    ))))
+}
+
+/// Execute the command in `options` against `band`.
+pub async fn execute<C>(
+    band: &mut Band<C>,
+    options: &Options,
+    ctx: C,
+    out: &mut impl Write,
+) -> Result<(), Error>
+where
+    C: Clone,
+{
+    if let Some(jobs) = options.jobs {
+        band.set_concurrency(jobs);
+    }
+    if options.keep_going {
+        band.set_failure_policy(FailurePolicy::KeepGoing);
+    }
+
+    match options.reporter {
+        ReporterKind::Pretty => band.add_reporter(ConsoleReporter::new()),
+        ReporterKind::Json => band.add_reporter(JsonReporter::stdout()),
+        ReporterKind::None => {}
+    }
+
+    match &options.command {
+        Command::Help => writeln!(out, "{}", USAGE)?,
+        Command::List => list(band, out)?,
+        Command::Order(tasks) => {
+            let tasks = tasks.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            order(band, &tasks, out)?;
+        }
+        Command::Graph(tasks) => {
+            let tasks = tasks.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            graph(band, &tasks, options.format, out)?;
+        }
+        Command::Stats(tasks) => {
+            let tasks = tasks.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            stats(band, &tasks, out)?;
+        }
+        Command::Run => {
+            let tasks = &options.invocations(band)?;
+            if options.dry_run {
+                let names = tasks.iter().map(|i| i.task.as_str()).collect::<Vec<_>>();
+                order(band, &names, out)?;
+            } else if options.watch {
+                watch(band, tasks, ctx).await?;
+            } else {
+                let summary = band.run_with(tasks, ctx).await?;
+                for warning in summary.warnings {
+                    eprintln!("band: warning: {}: {}", warning.task, warning.error);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute `options` against `band` on the runtime and return the process exit code.
+pub fn run<C>(mut band: Band<C>, options: &Options, ctx: C) -> i32
+where
+    C: Clone,
+{
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match runtime::block_on(execute(&mut band, options, ctx, &mut out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("band: {}", err);
+            err.exit_code()
+        }
+    }
+}
+
+/// Entry point for binaries with a registry of compiled tasks.
+///
+/// ```no_run
+/// # use band::{Band, cli};
+/// fn main() {
+///     let tasks = Band::<()>::new();
+///     std::process::exit(cli::main(tasks, ()));
+/// }
+/// ```
+pub fn main<C>(builder: BandBuilder<C>, ctx: C) -> i32
+where
+    C: Clone,
+{
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("band: {}\n\n{}", err, USAGE);
+            return USAGE_EXIT_CODE;
+        }
+    };
+
+    match builder.build() {
+        Ok(band) => run(band, &options, ctx),
+        Err(err) => {
+            eprintln!("band: {}", err);
+            err.exit_code()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{exec, TaskBuilder};
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(vec![
+            "-j",
+            "4",
+            "-k",
+            "--file=build.task",
+            "out=dist",
+            "build",
+            "test",
+        ])
+        .unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.words, vec!["build", "test"]);
+        assert_eq!(options.jobs, Some(4));
+        assert!(options.keep_going);
+        assert_eq!(options.file, Some(PathBuf::from("build.task")));
+        assert_eq!(options.variables, vec![("out".into(), "dist".into())]);
+        assert_eq!(options.reporter, ReporterKind::Pretty);
+
+        let options =
+            Options::parse(vec!["order", "build", "--dry-run", "-w", "--reporter=json"]).unwrap();
+        assert_eq!(options.reporter, ReporterKind::Json);
+        assert_eq!(options.command, Command::Order(vec!["build".into()]));
+        assert!(options.dry_run);
+        assert!(options.watch);
+
+        let options =
+            Options::parse(vec!["build", "--release", "--target=x", "-j", "2", "test"]).unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(
+            options.words,
+            vec!["build", "--release", "--target=x", "test"]
+        );
+        assert_eq!(options.jobs, Some(2));
+
+        assert!(Options::parse(vec!["run"]).is_err());
+        assert!(Options::parse(vec!["list", "--release"]).is_err());
+
+        let options = Options::parse(vec!["stats", "build"]).unwrap();
+        assert_eq!(options.command, Command::Stats(vec!["build".into()]));
+
+        let options = Options::parse(vec!["graph", "--format=json"]).unwrap();
+        assert_eq!(options.command, Command::Graph(vec![]));
+        assert_eq!(options.format, GraphFormat::Json);
+        assert!(Options::parse(vec!["--jobs", "0", "build"]).is_err());
+        assert!(Options::parse(vec!["--unknown"]).is_err());
+    }
+
+    #[test]
+    fn test_invocations() {
+        let band = Band::<()>::new()
+            .add_task(
+                "build",
+                TaskBuilder::new(exec("true"))
+                    .param(Param::flag("release"))
+                    .param(Param::string("target").default("host")),
+            )
+            .add_task("test", TaskBuilder::new(exec("true")))
+            .add_task(
+                "serve",
+                TaskBuilder::new(exec("true")).param(Param::flag("watch")),
+            )
+            .build()
+            .unwrap();
+        let invocations = |args: Vec<&str>| Options::parse(args).unwrap().invocations(&band);
+
+        assert_eq!(
+            invocations(vec![
+                "build",
+                "--target",
+                "x",
+                "--release",
+                "-j",
+                "2",
+                "test"
+            ])
+            .unwrap(),
+            vec![
+                Invocation::new("build")
+                    .arg("target", "x")
+                    .arg("release", true),
+                "test".into()
+            ]
+        );
+        assert_eq!(
+            invocations(vec!["run", "build", "--release", "test"]).unwrap(),
+            vec![Invocation::new("build").arg("release", true), "test".into()]
+        );
+        assert_eq!(
+            invocations(vec!["build", "--target", "x", "test"]).unwrap(),
+            vec![Invocation::new("build").arg("target", "x"), "test".into()]
+        );
+        assert_eq!(
+            invocations(vec!["build", "--target=a=b", "out=dist"]).unwrap(),
+            vec![Invocation::new("build").arg("target", "a=b")]
+        );
+        assert_eq!(
+            invocations(vec!["build", "--target", "-1"]).unwrap(),
+            vec![Invocation::new("build").arg("target", "-1")]
+        );
+        assert!(invocations(vec!["build", "--target"]).is_err());
+        assert!(invocations(vec!["build", "--target", "--release"]).is_err());
+        assert!(invocations(vec!["build", "-1"]).is_err());
+        assert!(matches!(
+            invocations(vec!["serve", "--watch"]),
+            Err(Error::Argument { task, .. }) if task == "serve"
+        ));
+    }
+}
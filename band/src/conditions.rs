@@ -0,0 +1,83 @@
+//! Predicates for [`TaskBuilder::only_if`](crate::TaskBuilder::only_if) and
+//! [`TaskBuilder::skip_if`](crate::TaskBuilder::skip_if).
+use super::TaskContext;
+use futures_util::future::{self, BoxFuture, FutureExt, Ready};
+use std::future::Future;
+use std::path::PathBuf;
+
+/// A predicate on the context deciding whether a task runs.
+pub(crate) type Condition<C> =
+    Box<dyn Fn(TaskContext<C>) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// A condition which holds when `predicate` returns `expect`.
+pub(crate) fn condition<C, F, U>(predicate: F, expect: bool) -> Condition<C>
+where
+    F: Fn(TaskContext<C>) -> U + Send + Sync + 'static,
+    U: Future<Output = bool> + Send + 'static,
+{
+    Box::new(move |ctx| predicate(ctx).map(move |ret| ret == expect).boxed())
+}
+
+/// True when `path` exists, relative to the working directory of the process.
+pub fn file_exists<C>(path: impl Into<PathBuf>) -> impl Fn(C) -> Ready<bool> + Send + Sync {
+    let path = path.into();
+    move |_| future::ready(path.exists())
+}
+
+/// True when the environment variable `name` is set and not empty.
+pub fn env_set<C>(name: impl Into<String>) -> impl Fn(C) -> Ready<bool> + Send + Sync {
+    let name = name.into();
+    move |_| future::ready(std::env::var_os(&name).is_some_and(|v| !v.is_empty()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{task, Log};
+    use crate::{Band, Event, SkipReason, TaskBuilder};
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_conditions() {
+        let skipped = Arc::new(Mutex::new(Vec::new()));
+        let recorded = skipped.clone();
+        let band = Band::new()
+            .add_task(
+                "missing",
+                TaskBuilder::new(task("missing")).only_if(file_exists("does/not/exist")),
+            )
+            .add_task(
+                "skipped",
+                TaskBuilder::new(task("skipped"))
+                    .skip_if(|ctx: TaskContext<Log>| async move { ctx.lock().unwrap().is_empty() }),
+            )
+            .add_task(
+                "env",
+                TaskBuilder::new(task("env"))
+                    .only_if(env_set("PATH"))
+                    .only_if(|_| async { true }),
+            )
+            .add_task(
+                "all",
+                TaskBuilder::new(task("all"))
+                    .add_dependency("missing")
+                    .add_dependency("skipped")
+                    .add_dependency("env"),
+            )
+            .concurrency(1)
+            .reporter(move |event: &Event| {
+                if let Event::TaskSkipped { name, reason, .. } = event {
+                    assert_eq!(*reason, SkipReason::Condition);
+                    recorded.lock().unwrap().push(name.clone());
+                }
+            })
+            .build()
+            .unwrap();
+
+        let log = Log::default();
+        block_on(band.run("all", log.clone())).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["env", "all"]);
+        assert_eq!(*skipped.lock().unwrap(), vec!["missing", "skipped"]);
+    }
+}
@@ -0,0 +1,149 @@
+use super::events::{Event, Reporter, RunId, TaskId};
+use super::outputs::Outputs;
+use super::params::Args;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// The task a context is passed to.
+pub(crate) struct TaskEnv {
+    pub run: RunId,
+    pub id: TaskId,
+    pub name: String,
+    pub args: Arc<Args>,
+    /// Outputs of the tasks of the run.
+    pub outputs: Arc<Outputs>,
+    pub reporters: Arc<[Arc<dyn Reporter>]>,
+}
+
+impl TaskEnv {
+    pub fn emit(&self, event: Event) {
+        for reporter in self.reporters.iter() {
+            reporter.report(&event);
+        }
+    }
+}
+
+/// What the action of a task is called with: the context of the run, along with
+/// the arguments of the task and the outputs of the tasks of the run.
+///
+/// It dereferences to the context of the run, so actions use it like the context itself.
+///
+/// ```
+/// # use band::{Band, Error, Invocation, Param, TaskBuilder, TaskContext};
+/// # use service::{service, Rejection};
+/// let band = Band::<()>::new()
+///     .add_task(
+///         "build",
+///         TaskBuilder::new(service!(|ctx: TaskContext<()>| async move {
+///             assert!(ctx.args().get_bool("release"));
+///             Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+///         }))
+///         .param(Param::flag("release")),
+///     )
+///     .build()
+///     .unwrap();
+/// let build = Invocation::new("build").arg("release", true);
+/// futures::executor::block_on(band.run_with(&[build], ())).unwrap();
+/// ```
+pub struct TaskContext<C> {
+    ctx: C,
+    task: Arc<TaskEnv>,
+}
+
+impl<C> TaskContext<C> {
+    pub(crate) fn new(ctx: C, task: Arc<TaskEnv>) -> TaskContext<C> {
+        TaskContext { ctx, task }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.task.id
+    }
+
+    /// Name of the task.
+    pub fn name(&self) -> &str {
+        &self.task.name
+    }
+
+    /// Validated arguments of the task.
+    pub fn args(&self) -> &Arc<Args> {
+        &self.task.args
+    }
+
+    /// Publish `value` as the output of this task, for the tasks depending on it.
+    ///
+    /// A task has one output per type; publishing again replaces it. Outputs last for
+    /// the run and are dropped when the task fails. They are not persisted, so a task
+    /// which published outputs is never skipped as up to date.
+    ///
+    /// ```
+    /// # use band::{Band, Error, TaskBuilder, TaskContext};
+    /// # use service::{service, Rejection};
+    /// struct Artifacts(Vec<String>);
+    ///
+    /// let band = Band::<()>::new()
+    ///     .add_task(
+    ///         "compile",
+    ///         TaskBuilder::new(service!(|ctx: TaskContext<()>| async move {
+    ///             ctx.publish(Artifacts(vec!["main.o".into()]));
+    ///             Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+    ///         })),
+    ///     )
+    ///     .add_task(
+    ///         "package",
+    ///         TaskBuilder::new(service!(|ctx: TaskContext<()>| async move {
+    ///             // Missing when `compile` was skipped by a condition.
+    ///             if let Some(artifacts) = ctx.output::<Artifacts>("compile") {
+    ///                 assert_eq!(artifacts.0, vec!["main.o"]);
+    ///             }
+    ///             Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+    ///         }))
+    ///         .add_dependency("compile"),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// futures::executor::block_on(band.run("package", ())).unwrap();
+    /// ```
+    pub fn publish<T: Send + Sync + 'static>(&self, value: T) {
+        self.task.outputs.insert(&self.task.name, value);
+    }
+
+    /// Output of type `T` published by `task` in this run.
+    ///
+    /// A task only sees the outputs of its dependencies reliably, as other tasks may not
+    /// have run yet. Tasks skipped by a condition publish nothing.
+    pub fn output<T: Send + Sync + 'static>(&self, task: &str) -> Option<Arc<T>> {
+        self.task.outputs.get(task)
+    }
+
+    pub(crate) fn env(&self) -> &Arc<TaskEnv> {
+        &self.task
+    }
+
+    /// The context of the run.
+    pub fn into_inner(self) -> C {
+        self.ctx
+    }
+}
+
+impl<C: Clone> Clone for TaskContext<C> {
+    fn clone(&self) -> Self {
+        TaskContext {
+            ctx: self.ctx.clone(),
+            task: self.task.clone(),
+        }
+    }
+}
+
+impl<C> Deref for TaskContext<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.ctx
+    }
+}
+
+impl<C> DerefMut for TaskContext<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.ctx
+    }
+}
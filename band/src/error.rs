@@ -1,17 +1,122 @@
+use super::file::ParseError;
 use std::error::Error as StdError;
 use std::fmt;
+use std::io;
+
+/// A task which failed during a run.
+#[derive(Debug)]
+pub struct Failure {
+    pub task: String,
+    pub error: Error,
+}
 
 #[derive(Debug)]
 pub enum Error {
     TaskNotFound(String),
     InvalidDepency(String),
+    UnknownDependency {
+        task: String,
+        dependency: String,
+    },
+    /// A dependency cycle, starting and ending with the same task.
+    Cycle(Vec<String>),
+    /// Two tasks with the same name were added to a band.
+    DuplicateTask(String),
+    /// Arguments of a task do not match its parameters.
+    Argument {
+        task: String,
+        message: String,
+    },
     Rejected,
+    Io(io::Error),
+    Parse(ParseError),
+    Pattern(String),
+    Spawn(runtime::SpawnError),
+    Command {
+        command: String,
+        status: Option<i32>,
+    },
+    /// A task did not finish within its timeout.
+    Timeout(std::time::Duration),
+    /// The run was cancelled through a [`CancelHandle`](crate::CancelHandle).
+    Cancelled,
+    /// Every task which failed in a run that kept going after the first failure.
+    Failed(Vec<Failure>),
     External(Box<dyn StdError + Send>),
 }
 
+impl Error {
+    /// Process exit code for the `band` command line.
+    ///
+    /// | code | error                                      |
+    /// |------|--------------------------------------------|
+    /// | 1    | a task failed or was rejected              |
+    /// | 2    | invalid task arguments                     |
+    /// | 3    | unknown task                               |
+    /// | 4    | invalid, unknown, cyclic or duplicate task |
+    /// | 5    | task file or pattern could not be parsed   |
+    /// | 6    | io error                                   |
+    /// | 130  | the run was cancelled                      |
+    ///
+    /// 2 is shared with command line usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Rejected
+            | Error::Spawn(_)
+            | Error::Command { .. }
+            | Error::Timeout(_)
+            | Error::Failed(_)
+            | Error::External(_) => 1,
+            Error::Argument { .. } => 2,
+            Error::TaskNotFound(_) => 3,
+            Error::InvalidDepency(_)
+            | Error::UnknownDependency { .. }
+            | Error::Cycle(_)
+            | Error::DuplicateTask(_) => 4,
+            Error::Parse(_) | Error::Pattern(_) => 5,
+            Error::Io(_) => 6,
+            Error::Cancelled => 130,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(())
+        match self {
+            Error::TaskNotFound(name) => write!(f, "task '{}' not found", name),
+            Error::InvalidDepency(msg) => write!(f, "invalid dependency: {}", msg),
+            Error::UnknownDependency { task, dependency } => write!(
+                f,
+                "task '{}' depends on unknown task '{}'",
+                task, dependency
+            ),
+            Error::Cycle(cycle) => write!(f, "dependency cycle: {}", cycle.join(" -> ")),
+            Error::DuplicateTask(name) => write!(f, "task '{}' is defined more than once", name),
+            Error::Argument { task, message } => write!(f, "task '{}': {}", task, message),
+            Error::Rejected => write!(f, "task rejected"),
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Parse(err) => write!(f, "parse error: {}", err),
+            Error::Pattern(err) => write!(f, "invalid pattern: {}", err),
+            Error::Spawn(err) => write!(f, "spawn error: {}", err),
+            Error::Command {
+                command,
+                status: Some(status),
+            } => write!(f, "command '{}' exited with status {}", command, status),
+            Error::Command {
+                command,
+                status: None,
+            } => write!(f, "command '{}' was terminated", command),
+            Error::Timeout(timeout) => write!(f, "timed out after {:?}", timeout),
+            Error::Cancelled => write!(f, "run cancelled"),
+            Error::Failed(failures) => {
+                write!(f, "{} task(s) failed", failures.len())?;
+                for failure in failures {
+                    write!(f, "\n  {}: {}", failure.task, failure.error)?;
+                }
+                Ok(())
+            }
+            Error::External(err) => write!(f, "{}", err),
+        }
     }
 }
 
@@ -22,3 +127,21 @@ impl From<Box<dyn StdError + Send>> for Error {
         Error::External(error)
     }
 }
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+impl From<runtime::SpawnError> for Error {
+    fn from(error: runtime::SpawnError) -> Self {
+        Error::Spawn(error)
+    }
+}
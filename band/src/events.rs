@@ -0,0 +1,487 @@
+use futures_channel::mpsc;
+use futures_util::stream::Stream;
+use serde_json::json;
+use slotmap::{DefaultKey, Key};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Identifies a task within a band. Ids are stable for the lifetime of the band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<DefaultKey> for TaskId {
+    fn from(key: DefaultKey) -> Self {
+        // Tasks are never removed from a band, so the slot index alone is unique.
+        TaskId(key.data().as_ffi() & 0xffff_ffff)
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies a run of a band, telling apart the events of runs overlapping each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RunId(u64);
+
+impl RunId {
+    pub(crate) fn new(id: u64) -> RunId {
+        RunId(id)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Inputs and outputs are unchanged since the task last succeeded.
+    UpToDate,
+    /// A task this task depends on failed.
+    DependencyFailed,
+    /// A condition of the task did not hold.
+    Condition,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::UpToDate => write!(f, "up to date"),
+            SkipReason::DependencyFailed => write!(f, "dependency failed"),
+            SkipReason::Condition => write!(f, "condition not met"),
+        }
+    }
+}
+
+/// Output stream of a command run by a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl fmt::Display for OutputStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputStream::Stdout => write!(f, "stdout"),
+            OutputStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    RunStarted {
+        run: RunId,
+        tasks: Vec<String>,
+        /// Expected duration of the run, when the band has a history of earlier runs.
+        estimate: Option<Duration>,
+    },
+    TaskQueued {
+        run: RunId,
+        id: TaskId,
+        name: String,
+    },
+    TaskStarted {
+        run: RunId,
+        id: TaskId,
+        name: String,
+    },
+    TaskSkipped {
+        run: RunId,
+        id: TaskId,
+        name: String,
+        reason: SkipReason,
+    },
+    TaskRetrying {
+        run: RunId,
+        id: TaskId,
+        name: String,
+        /// The attempt about to start, starting at 2.
+        attempt: u32,
+        error: String,
+    },
+    /// A line written by a command the task runs.
+    TaskOutput {
+        run: RunId,
+        id: TaskId,
+        name: String,
+        stream: OutputStream,
+        line: String,
+    },
+    TaskFinished {
+        run: RunId,
+        id: TaskId,
+        name: String,
+        duration: Duration,
+    },
+    TaskFailed {
+        run: RunId,
+        id: TaskId,
+        name: String,
+        duration: Duration,
+        error: String,
+    },
+    /// The task was running when the run was cancelled.
+    TaskCancelled {
+        run: RunId,
+        id: TaskId,
+        name: String,
+    },
+    RunFinished {
+        run: RunId,
+        duration: Duration,
+        success: bool,
+    },
+    /// Something went wrong which does not fail the run, like saving its history.
+    Warning { message: String },
+}
+
+impl Event {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::RunStarted { .. } => "run_started",
+            Event::TaskQueued { .. } => "task_queued",
+            Event::TaskStarted { .. } => "task_started",
+            Event::TaskSkipped { .. } => "task_skipped",
+            Event::TaskRetrying { .. } => "task_retrying",
+            Event::TaskOutput { .. } => "task_output",
+            Event::TaskFinished { .. } => "task_finished",
+            Event::TaskFailed { .. } => "task_failed",
+            Event::TaskCancelled { .. } => "task_cancelled",
+            Event::RunFinished { .. } => "run_finished",
+            Event::Warning { .. } => "warning",
+        }
+    }
+
+    /// The run the event is part of. Warnings are not part of any run.
+    pub fn run(&self) -> Option<RunId> {
+        match self {
+            Event::RunStarted { run, .. }
+            | Event::TaskQueued { run, .. }
+            | Event::TaskStarted { run, .. }
+            | Event::TaskSkipped { run, .. }
+            | Event::TaskRetrying { run, .. }
+            | Event::TaskOutput { run, .. }
+            | Event::TaskFinished { run, .. }
+            | Event::TaskFailed { run, .. }
+            | Event::TaskCancelled { run, .. }
+            | Event::RunFinished { run, .. } => Some(*run),
+            Event::Warning { .. } => None,
+        }
+    }
+}
+
+/// Receives the events of every run of a band.
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: &Event);
+}
+
+impl<F> Reporter for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn report(&self, event: &Event) {
+        (self)(event)
+    }
+}
+
+/// Forwards events into a channel. Created by [`Band::subscribe`](crate::Band::subscribe).
+pub(crate) struct ChannelReporter(mpsc::UnboundedSender<Event>);
+
+impl Reporter for ChannelReporter {
+    fn report(&self, event: &Event) {
+        self.0.unbounded_send(event.clone()).ok();
+    }
+}
+
+pub(crate) fn channel() -> (ChannelReporter, impl Stream<Item = Event>) {
+    let (sender, receiver) = mpsc::unbounded();
+    (ChannelReporter(sender), receiver)
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() >= 60 {
+        format!(
+            "{}m{:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        )
+    } else if duration.as_millis() >= 1000 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Human readable progress on stderr.
+#[derive(Default)]
+pub struct ConsoleReporter {
+    progress: Mutex<(usize, usize)>,
+}
+
+impl ConsoleReporter {
+    pub fn new() -> ConsoleReporter {
+        ConsoleReporter::default()
+    }
+
+    fn done(&self) -> String {
+        let mut progress = self.progress.lock().unwrap();
+        progress.0 += 1;
+        format!("[{}/{}]", progress.0, progress.1)
+    }
+}
+
+impl Reporter for ConsoleReporter {
+    fn report(&self, event: &Event) {
+        match event {
+            Event::RunStarted {
+                tasks, estimate, ..
+            } => {
+                *self.progress.lock().unwrap() = (0, tasks.len());
+                if let Some(estimate) = estimate {
+                    eprintln!(
+                        "running {} tasks, about {}",
+                        tasks.len(),
+                        format_duration(*estimate)
+                    );
+                }
+            }
+            Event::TaskQueued { .. } => {}
+            Event::TaskStarted { name, .. } => eprintln!("  -> {}", name),
+            Event::TaskSkipped { name, reason, .. } => {
+                eprintln!("{} {} ({})", self.done(), name, reason)
+            }
+            Event::TaskRetrying {
+                name,
+                attempt,
+                error,
+                ..
+            } => eprintln!("  -> {} failed: {}, attempt {}", name, error, attempt),
+            Event::TaskOutput {
+                name, stream, line, ..
+            } => match stream {
+                OutputStream::Stdout => println!("{} | {}", name, line),
+                OutputStream::Stderr => eprintln!("{} | {}", name, line),
+            },
+            Event::TaskFinished { name, duration, .. } => {
+                eprintln!(
+                    "{} {} done in {}",
+                    self.done(),
+                    name,
+                    format_duration(*duration)
+                )
+            }
+            Event::TaskFailed {
+                name,
+                duration,
+                error,
+                ..
+            } => eprintln!(
+                "{} {} failed after {}: {}",
+                self.done(),
+                name,
+                format_duration(*duration),
+                error
+            ),
+            Event::TaskCancelled { name, .. } => eprintln!("{} {} cancelled", self.done(), name),
+            Event::RunFinished {
+                duration, success, ..
+            } => eprintln!(
+                "{} in {}",
+                if *success { "finished" } else { "failed" },
+                format_duration(*duration)
+            ),
+            Event::Warning { message } => eprintln!("warning: {}", message),
+        }
+    }
+}
+
+/// One JSON object per event and line, for CI logs.
+pub struct JsonReporter<W> {
+    writer: Mutex<W>,
+}
+
+impl JsonReporter<io::Stdout> {
+    pub fn stdout() -> JsonReporter<io::Stdout> {
+        JsonReporter::new(io::stdout())
+    }
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub fn new(writer: W) -> JsonReporter<W> {
+        JsonReporter {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+pub(crate) fn to_json(event: &Event) -> serde_json::Value {
+    let mut value = match event {
+        Event::RunStarted {
+            tasks, estimate, ..
+        } => json!({
+            "tasks": tasks,
+            "estimate_ms": estimate.map(|d| d.as_millis() as u64),
+        }),
+        Event::TaskQueued { id, name, .. } | Event::TaskStarted { id, name, .. } => {
+            json!({ "id": id.as_u64(), "task": name })
+        }
+        Event::TaskSkipped {
+            id, name, reason, ..
+        } => {
+            json!({ "id": id.as_u64(), "task": name, "reason": reason.to_string() })
+        }
+        Event::TaskRetrying {
+            id,
+            name,
+            attempt,
+            error,
+            ..
+        } => json!({
+            "id": id.as_u64(),
+            "task": name,
+            "attempt": attempt,
+            "error": error,
+        }),
+        Event::TaskOutput {
+            id,
+            name,
+            stream,
+            line,
+            ..
+        } => json!({
+            "id": id.as_u64(),
+            "task": name,
+            "stream": stream.to_string(),
+            "line": line,
+        }),
+        Event::TaskFinished {
+            id, name, duration, ..
+        } => json!({
+            "id": id.as_u64(),
+            "task": name,
+            "duration_ms": duration.as_millis() as u64,
+        }),
+        Event::TaskFailed {
+            id,
+            name,
+            duration,
+            error,
+            ..
+        } => json!({
+            "id": id.as_u64(),
+            "task": name,
+            "duration_ms": duration.as_millis() as u64,
+            "error": error,
+        }),
+        Event::TaskCancelled { id, name, .. } => json!({ "id": id.as_u64(), "task": name }),
+        Event::RunFinished {
+            duration, success, ..
+        } => json!({
+            "duration_ms": duration.as_millis() as u64,
+            "success": success,
+        }),
+        Event::Warning { message } => json!({ "message": message }),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    if let Some(run) = event.run() {
+        value["run"] = json!(run.as_u64());
+    }
+    value["event"] = json!(event.kind());
+    value["timestamp"] = json!(timestamp);
+    value
+}
+
+impl<W: Write + Send> Reporter for JsonReporter<W> {
+    fn report(&self, event: &Event) {
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", to_json(event)).ok();
+        writer.flush().ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Band, Error, TaskBuilder, TaskContext};
+    use futures::executor::block_on;
+    use futures_util::StreamExt;
+    use service::{service, Rejection};
+
+    #[test]
+    fn test_events() {
+        let json = std::sync::Arc::new(JsonReporter::new(Vec::new()));
+        let mut band = Band::<()>::new()
+            .add_task(
+                "build",
+                TaskBuilder::new(service!(|ctx: TaskContext<()>| async move {
+                    Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+                }))
+                .add_dependency("fail"),
+            )
+            .add_task(
+                "fail",
+                TaskBuilder::new(service!(|_: TaskContext<()>| async move {
+                    Result::<(TaskContext<()>, ()), _>::Err(Rejection::Err(Error::Rejected))
+                })),
+            )
+            .concurrency(1)
+            .build()
+            .unwrap();
+
+        let events = band.subscribe();
+        let reporter = json.clone();
+        band.add_reporter(move |event: &Event| reporter.report(event));
+
+        assert!(block_on(band.run("build", ())).is_err());
+        drop(band);
+
+        let kinds = block_on(events.map(|e| e.kind()).collect::<Vec<_>>());
+        assert_eq!(
+            kinds,
+            vec![
+                "run_started",
+                "task_queued",
+                "task_queued",
+                "task_started",
+                "task_failed",
+                "run_finished"
+            ]
+        );
+
+        let json = std::sync::Arc::try_unwrap(json).ok().unwrap().into_inner();
+        let lines = String::from_utf8(json).unwrap();
+        let failed: serde_json::Value =
+            serde_json::from_str(lines.lines().nth(4).unwrap()).unwrap();
+        assert_eq!(failed["event"], "task_failed");
+        assert_eq!(failed["task"], "fail");
+        assert_eq!(failed["error"], "task rejected");
+        assert!(failed["run"].is_u64());
+    }
+}
@@ -0,0 +1,475 @@
+use super::context::{TaskContext, TaskEnv};
+use super::events::{Event, OutputStream};
+use super::Error;
+use futures_util::future::{BoxFuture, FutureExt};
+use service::{Rejection, Service};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Output of a command run by a [`ShellAction`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Output {
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+type Capture<C> = Arc<dyn Fn(&mut C, Output) + Send + Sync>;
+
+/// Runs a single program with its arguments, environment and working directory.
+///
+/// Every line the program writes is reported to the reporters of the band as an
+/// [`Event::TaskOutput`]. A non-zero exit status fails the task with [`Error::Command`].
+///
+/// ```
+/// # use band::{exec, Band, TaskBuilder};
+/// let band = Band::<()>::new()
+///     .add_task(
+///         "build",
+///         TaskBuilder::new(exec("cargo").arg("build").env("CARGO_TERM_COLOR", "never")),
+///     )
+///     .build()
+///     .unwrap();
+/// ```
+pub struct ShellAction<C> {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+    capture: Option<Capture<C>>,
+}
+
+/// Shorthand for [`ShellAction::new`].
+pub fn exec<C>(program: impl Into<String>) -> ShellAction<C> {
+    ShellAction::new(program)
+}
+
+impl<C> ShellAction<C> {
+    pub fn new(program: impl Into<String>) -> ShellAction<C> {
+        ShellAction {
+            program: program.into(),
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            capture: None,
+        }
+    }
+
+    /// Run `command` with the shell of the platform.
+    pub fn shell(command: impl Into<String>) -> ShellAction<C> {
+        if cfg!(windows) {
+            ShellAction::new("cmd").arg("/C").arg(command.into())
+        } else {
+            ShellAction::new("sh").arg("-c").arg(command.into())
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn cwd(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(path.into());
+        self
+    }
+
+    /// Collect the output of the program and hand it to `capture` together with
+    /// the context once the program succeeded.
+    pub fn capture<F>(mut self, capture: F) -> Self
+    where
+        F: Fn(&mut C, Output) + Send + Sync + 'static,
+    {
+        self.capture = Some(Arc::new(capture));
+        self
+    }
+
+    /// The program and its arguments as they would be typed in a shell.
+    fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|s| {
+                if s.is_empty() || s.contains(char::is_whitespace) {
+                    format!("'{}'", s.replace('\'', "'\\''"))
+                } else {
+                    s.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl<C> Clone for ShellAction<C> {
+    fn clone(&self) -> Self {
+        ShellAction {
+            program: self.program.clone(),
+            args: self.args.clone(),
+            env: self.env.clone(),
+            cwd: self.cwd.clone(),
+            capture: self.capture.clone(),
+        }
+    }
+}
+
+impl<C> fmt::Debug for ShellAction<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShellAction")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("cwd", &self.cwd)
+            .field("capture", &self.capture.is_some())
+            .finish()
+    }
+}
+
+/// A running program, shared by the thread waiting for it and the future running it.
+#[derive(Default)]
+struct Process {
+    state: Mutex<ProcessState>,
+    reaped: Condvar,
+}
+
+#[derive(Default)]
+struct ProcessState {
+    /// The program, from when it started until it was reaped.
+    child: Option<Child>,
+    killed: bool,
+}
+
+/// Kills the program when the future running it is dropped before it exited,
+/// and waits for it to be reaped.
+struct KillOnDrop(Arc<Process>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.killed = true;
+        if let Some(child) = state.child.as_mut() {
+            child.kill().ok();
+        }
+        while state.child.is_some() {
+            state = self.0.reaped.wait(state).unwrap();
+        }
+    }
+}
+
+/// Block until `child` exited without reaping it, so it can still be killed meanwhile.
+#[cfg(unix)]
+fn exit_waiter(child: &Child) -> impl FnOnce() {
+    let pid = child.id() as libc::id_t;
+    move || loop {
+        // Safety: `info` is a valid siginfo_t to write the state of the child to.
+        let ret = unsafe {
+            let mut info = std::mem::zeroed::<libc::siginfo_t>();
+            libc::waitid(libc::P_PID, pid, &mut info, libc::WEXITED | libc::WNOWAIT)
+        };
+        if ret == 0 || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+            break;
+        }
+    }
+}
+
+/// Block until `child` exited. The handle stays valid as the child is only dropped
+/// once it is reaped.
+#[cfg(windows)]
+fn exit_waiter(child: &Child) -> impl FnOnce() {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Threading::{WaitForSingleObject, INFINITE};
+
+    let handle = child.as_raw_handle() as usize;
+    // Safety: the handle is valid for as long as the child is not dropped.
+    move || unsafe {
+        WaitForSingleObject(handle as _, INFINITE);
+    }
+}
+
+/// Time the pipes are read for once the program exited. Programs it started
+/// in the background may keep them open.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Output read from the pipes of a program.
+#[derive(Default)]
+struct Collected {
+    stdout: String,
+    stderr: String,
+    /// Set once the output is no longer wanted.
+    stopped: bool,
+}
+
+/// Reads the pipes of a program on threads of their own.
+struct Readers {
+    task: Option<Arc<TaskEnv>>,
+    capture: bool,
+    collected: Arc<Mutex<Collected>>,
+    /// Dropped by every reader once its pipe is closed or it was stopped.
+    done: mpsc::Sender<()>,
+}
+
+impl Readers {
+    fn spawn(&self, pipe: impl Read + Send + 'static, stream: OutputStream) {
+        let task = self.task.clone();
+        let capture = self.capture;
+        let collected = self.collected.clone();
+        let done = self.done.clone();
+        std::thread::spawn(move || {
+            read_lines(pipe, stream, task.as_deref(), capture, &collected);
+            drop(done);
+        });
+    }
+}
+
+/// Forward the lines of `pipe` to the reporters of the task, collecting them when
+/// `capture` is set, until the pipe is closed or the output is no longer wanted.
+fn read_lines(
+    pipe: impl Read,
+    stream: OutputStream,
+    task: Option<&TaskEnv>,
+    capture: bool,
+    collected: &Mutex<Collected>,
+) {
+    let mut reader = BufReader::new(pipe);
+    let mut buf = Vec::new();
+    while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+        if n == 0 {
+            break;
+        }
+        let mut collected = collected.lock().unwrap();
+        if collected.stopped {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        if capture {
+            match stream {
+                OutputStream::Stdout => collected.stdout.push_str(&line),
+                OutputStream::Stderr => collected.stderr.push_str(&line),
+            }
+        }
+        if let Some(task) = task {
+            task.emit(Event::TaskOutput {
+                run: task.run,
+                id: task.id,
+                name: task.name.clone(),
+                stream,
+                line: line.trim_end_matches(&['\r', '\n'][..]).to_owned(),
+            });
+        }
+        buf.clear();
+    }
+}
+
+fn run(
+    mut command: Command,
+    task: Option<Arc<TaskEnv>>,
+    capture: bool,
+    process: Arc<Process>,
+) -> Result<Output, Error> {
+    let pipe = task.is_some() || capture;
+    if pipe {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = command.spawn()?;
+    let wait_exit = exit_waiter(&child);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    {
+        let mut state = process.state.lock().unwrap();
+        if state.killed {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(Error::Cancelled);
+        }
+        state.child = Some(child);
+    }
+
+    let (done, drained) = mpsc::channel();
+    let readers = Readers {
+        task,
+        capture,
+        collected: Arc::new(Mutex::new(Collected::default())),
+        done,
+    };
+    if let Some(pipe) = stdout {
+        readers.spawn(pipe, OutputStream::Stdout);
+    }
+    if let Some(pipe) = stderr {
+        readers.spawn(pipe, OutputStream::Stderr);
+    }
+    let Readers { collected, .. } = readers;
+
+    wait_exit();
+    let (status, killed) = {
+        let mut state = process.state.lock().unwrap();
+        let status = state.child.take().map(|mut child| child.wait());
+        process.reaped.notify_all();
+        (status, state.killed)
+    };
+    let status = match status {
+        Some(status) if !killed => status?,
+        _ => {
+            collected.lock().unwrap().stopped = true;
+            return Err(Error::Cancelled);
+        }
+    };
+
+    // Every reader is gone once both pipes are closed.
+    drained.recv_timeout(DRAIN_TIMEOUT).ok();
+    let mut collected = collected.lock().unwrap();
+    collected.stopped = true;
+    Ok(Output {
+        status: status.code(),
+        stdout: std::mem::take(&mut collected.stdout),
+        stderr: std::mem::take(&mut collected.stderr),
+    })
+}
+
+impl<C> Service<TaskContext<C>> for ShellAction<C>
+where
+    C: Send + 'static,
+{
+    type Output = (TaskContext<C>, ());
+    type Error = Error;
+    type Future =
+        BoxFuture<'static, Result<(TaskContext<C>, ()), Rejection<TaskContext<C>, Error>>>;
+
+    fn call(&self, mut ctx: TaskContext<C>) -> Self::Future {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command.envs(self.env.iter().map(|(k, v)| (k, v)));
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        let line = self.command_line();
+        let capture = self.capture.clone();
+        let task = Some(ctx.env().clone()).filter(|task| !task.reporters.is_empty());
+        let process = Arc::new(Process::default());
+        let guard = KillOnDrop(process.clone());
+
+        async move {
+            let ret = runtime::spawn_blocking({
+                let capture = capture.is_some();
+                move || run(command, task, capture, process)
+            })
+            .await;
+            drop(guard);
+
+            let output = match ret {
+                Ok(Ok(output)) => output,
+                Ok(Err(err)) => return Err(Rejection::Err(err)),
+                Err(err) => return Err(Rejection::Err(err.into())),
+            };
+            if output.status != Some(0) {
+                return Err(Rejection::Err(Error::Command {
+                    command: line,
+                    status: output.status,
+                }));
+            }
+            if let Some(capture) = capture {
+                capture(&mut *ctx, output);
+            }
+            Ok((ctx, ()))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod test {
+    use super::*;
+    use crate::{Band, TaskBuilder};
+
+    #[test]
+    fn test_exec() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let recorded = lines.clone();
+        let band = Band::new()
+            .add_task(
+                "greet",
+                TaskBuilder::new(
+                    exec("sh")
+                        .args(vec!["-c", "echo $GREETING; echo oops >&2"])
+                        .env("GREETING", "hello")
+                        .capture(|ctx: &mut Arc<Mutex<Option<Output>>>, output| {
+                            *ctx.lock().unwrap() = Some(output)
+                        }),
+                ),
+            )
+            .add_task("fail", TaskBuilder::new(ShellAction::shell("exit 3")))
+            .reporter(move |event: &Event| {
+                if let Event::TaskOutput { stream, line, .. } = event {
+                    recorded.lock().unwrap().push((*stream, line.clone()));
+                }
+            })
+            .build()
+            .unwrap();
+
+        let ctx = Arc::new(Mutex::new(None));
+        runtime::block_on(band.run("greet", ctx.clone())).unwrap();
+        let output = ctx.lock().unwrap().take().unwrap();
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+
+        let mut lines = lines.lock().unwrap().clone();
+        lines.sort_by_key(|(stream, _)| *stream == OutputStream::Stderr);
+        assert_eq!(
+            lines,
+            vec![
+                (OutputStream::Stdout, "hello".to_owned()),
+                (OutputStream::Stderr, "oops".to_owned())
+            ]
+        );
+
+        let err = runtime::block_on(band.run("fail", ctx)).unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+        match err {
+            Error::Command { command, status } => {
+                assert_eq!(command, "sh -c 'exit 3'");
+                assert_eq!(status, Some(3));
+            }
+            err => panic!("expected command error, got: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_background_process() {
+        // The background process keeps the pipes open after the shell exited.
+        let band = Band::new()
+            .add_task(
+                "serve",
+                TaskBuilder::new(exec("sh").arg("-c").arg("sleep 5 & echo started").capture(
+                    |ctx: &mut Arc<Mutex<Option<Output>>>, output| {
+                        *ctx.lock().unwrap() = Some(output)
+                    },
+                )),
+            )
+            .build()
+            .unwrap();
+
+        let ctx = Arc::new(Mutex::new(None));
+        let start = std::time::Instant::now();
+        runtime::block_on(band.run("serve", ctx.clone())).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(ctx.lock().unwrap().take().unwrap().stdout, "started\n");
+    }
+}
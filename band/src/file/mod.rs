@@ -0,0 +1,316 @@
+mod parser;
+
+pub use self::parser::{Command, Document, ParseError, Span, TaskDecl, Variable};
+
+use super::{Band, BandBuilder, Error, ShellAction, TaskBuilder, TaskContext, TaskDesc};
+use futures_util::future::{BoxFuture, FutureExt};
+use service::{Rejection, Service};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A parsed `.task` file.
+///
+/// ```text
+/// out = dest
+///
+/// # Remove the output directory
+/// task clean {
+///     rm -rf $out
+/// }
+///
+/// task Copy files depends on clean inputs tasks/* outputs "${out}/*" {
+///     cp ./tasks/* ${out}
+/// }
+/// ```
+///
+/// Every line in a task body is run as a separate shell command, and comment lines
+/// directly above a task become its description. Relative `inputs` and `outputs`
+/// globs are resolved against the directory of the file.
+/// Variables are interpolated when the file is turned into tasks, so they can be
+/// overridden with [`TaskFile::set`].
+#[derive(Debug, Clone)]
+pub struct TaskFile {
+    document: Document,
+    overrides: HashMap<String, String>,
+    root: Option<PathBuf>,
+}
+
+impl TaskFile {
+    pub fn parse(input: &str) -> Result<TaskFile, ParseError> {
+        Ok(TaskFile {
+            document: parser::parse(input)?,
+            overrides: HashMap::new(),
+            root: None,
+        })
+    }
+
+    /// Load a task file from disk. Commands are run from the directory containing the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<TaskFile, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut file = TaskFile::parse(&content)?;
+        file.root = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| p.to_path_buf());
+        Ok(file)
+    }
+
+    pub fn tasks(&self) -> &[TaskDecl] {
+        &self.document.tasks
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        &self.document.variables
+    }
+
+    /// Override the value of a variable.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.overrides.insert(name.into(), value.into());
+        self
+    }
+
+    fn resolve_variables(&self) -> HashMap<String, String> {
+        let mut vars = HashMap::<String, String>::new();
+        for var in &self.document.variables {
+            let value = match self.overrides.get(&var.name) {
+                Some(value) => value.clone(),
+                None => parser::interpolate(&var.value, |name| vars.get(name).cloned()),
+            };
+            vars.insert(var.name.clone(), value);
+        }
+        for (name, value) in &self.overrides {
+            vars.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        vars
+    }
+
+    pub fn into_tasks<C>(self) -> Vec<TaskDesc<C>>
+    where
+        C: Send + 'static,
+    {
+        let vars = self.resolve_variables();
+        let root = self.root;
+
+        self.document
+            .tasks
+            .into_iter()
+            .map(|task| {
+                let commands = task
+                    .commands
+                    .iter()
+                    .map(|cmd| {
+                        let line = parser::interpolate(&cmd.line, |name| vars.get(name).cloned());
+                        match &root {
+                            Some(root) => ShellAction::shell(line).cwd(root.clone()),
+                            None => ShellAction::shell(line),
+                        }
+                    })
+                    .collect();
+                let mut builder = TaskBuilder::new(Commands(Arc::new(commands)));
+                if let Some(description) = task.description {
+                    builder = builder.description(description);
+                }
+                let glob = |glob: &String| {
+                    let glob = parser::interpolate(glob, |name| vars.get(name).cloned());
+                    match &root {
+                        Some(root) if Path::new(&glob).is_relative() => {
+                            root.join(glob).to_string_lossy().into_owned()
+                        }
+                        _ => glob,
+                    }
+                };
+                for input in &task.inputs {
+                    builder = builder.input(glob(input));
+                }
+                for output in &task.outputs {
+                    builder = builder.output(glob(output));
+                }
+                task.dependencies
+                    .into_iter()
+                    .fold(builder, |builder, dep| builder.add_dependency(dep))
+                    .build(task.name)
+            })
+            .collect()
+    }
+
+    /// Directory commands are run from, if the file was loaded from disk.
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    pub fn into_builder<C>(self) -> BandBuilder<C>
+    where
+        C: Send + 'static,
+    {
+        Band::new().add_tasks(self.into_tasks())
+    }
+
+    pub fn into_band<C>(self) -> Result<Band<C>, Error>
+    where
+        C: Send + 'static,
+    {
+        self.into_builder().build()
+    }
+}
+
+/// Runs the commands of a task one after another.
+/// The first command exiting with a non-zero status fails the task.
+struct Commands<C>(Arc<Vec<ShellAction<C>>>);
+
+impl<C> Service<TaskContext<C>> for Commands<C>
+where
+    C: Send + 'static,
+{
+    type Output = (TaskContext<C>, ());
+    type Error = Error;
+    type Future =
+        BoxFuture<'static, Result<(TaskContext<C>, ()), Rejection<TaskContext<C>, Error>>>;
+
+    fn call(&self, mut ctx: TaskContext<C>) -> Self::Future {
+        let commands = self.0.clone();
+        async move {
+            for command in commands.iter() {
+                ctx = command.call(ctx).await?.0;
+            }
+            Ok((ctx, ()))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let file = TaskFile::parse(
+            r#"
+# output directory
+out = dest
+target = "${out}/files"
+
+# Remove the
+# output directory
+task clean {
+    rm -rf $out
+}
+
+task Copy files depends on clean, "make dirs" inputs tasks/*.rs outputs "${out}/*" {
+    cp ./tasks/* ${target} \
+        --verbose
+    # comment
+    echo "{ done }"
+}
+
+task make dirs { mkdir -p $target }
+"#,
+        )
+        .unwrap();
+
+        let tasks = file.tasks();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(
+            tasks[0].description.as_deref(),
+            Some("Remove the output directory")
+        );
+        assert_eq!(tasks[1].name, "Copy files");
+        assert_eq!(tasks[1].description, None);
+        assert_eq!(tasks[1].dependencies, vec!["clean", "make dirs"]);
+        assert_eq!(tasks[1].inputs, vec!["tasks/*.rs"]);
+        assert_eq!(tasks[1].outputs, vec!["${out}/*"]);
+        assert_eq!(tasks[1].commands.len(), 2);
+        assert_eq!(tasks[1].commands[0].span, Span { line: 13, column: 5 });
+        assert_eq!(tasks[2].commands[0].line, "mkdir -p $target");
+
+        let vars = file.resolve_variables();
+        assert_eq!(vars["target"], "dest/files");
+        assert_eq!(
+            parser::interpolate("cp $$HOME ${target} $out $missing", |name| vars
+                .get(name)
+                .cloned()),
+            "cp $HOME dest/files dest $missing"
+        );
+
+        let band = file.into_band::<()>().unwrap();
+        assert_eq!(
+            band.get_tasks(&["Copy files"]).unwrap(),
+            vec!["clean", "make dirs", "Copy files"]
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_run() {
+        use crate::{Event, OutputStream};
+        use std::sync::Mutex;
+
+        let mut file = TaskFile::parse(
+            r#"
+task greet {
+    echo $greeting
+    echo 'done'
+}
+
+task fail {
+    exit 3
+    echo never
+}
+"#,
+        )
+        .unwrap();
+        file.set("greeting", "hello");
+        let mut band = file.into_band::<()>().unwrap();
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let recorded = lines.clone();
+        band.add_reporter(move |event: &Event| {
+            if let Event::TaskOutput { stream, line, .. } = event {
+                recorded.lock().unwrap().push((*stream, line.clone()));
+            }
+        });
+
+        runtime::block_on(band.run("greet", ())).unwrap();
+        runtime::block_on(band.run("fail", ())).unwrap_err();
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                (OutputStream::Stdout, "hello".to_owned()),
+                (OutputStream::Stdout, "done".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_comments() {
+        let file = TaskFile::parse(
+            r#"
+task build {
+    # don't {
+    echo "{ done }"
+    # }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(file.tasks().len(), 1);
+        let commands = &file.tasks()[0].commands;
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].line, r#"echo "{ done }""#);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = TaskFile::parse("task build {\n  echo ${out\n}").unwrap_err();
+        assert_eq!(err.span, Span { line: 2, column: 8 });
+
+        let err = TaskFile::parse("out = dest\ntask {}").unwrap_err();
+        assert_eq!(err.span, Span { line: 2, column: 6 });
+        assert_eq!(err.message, "expected task name");
+
+        let err = TaskFile::parse("task build\n  echo").unwrap_err();
+        assert_eq!(err.span, Span { line: 2, column: 3 });
+    }
+}
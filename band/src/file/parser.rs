@@ -0,0 +1,559 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Position in a task file. Both line and column are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub line: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskDecl {
+    pub name: String,
+    /// Comment lines directly above the task.
+    pub description: Option<String>,
+    pub dependencies: Vec<String>,
+    /// Globs of files the task reads.
+    pub inputs: Vec<String>,
+    /// Globs of files the task produces.
+    pub outputs: Vec<String>,
+    pub commands: Vec<Command>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    pub variables: Vec<Variable>,
+    pub tasks: Vec<TaskDecl>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Clause {
+    DependsOn,
+    Inputs,
+    Outputs,
+}
+
+#[derive(Clone)]
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_word(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '{' | '}' | ',' | '"' | '#')
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            chars: input.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            span: self.span(),
+            message: message.into(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_inline_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' || !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    /// Like `skip_whitespace`, but returns the comment lines directly above the next token.
+    fn skip_whitespace_comments(&mut self) -> Vec<String> {
+        let mut comments = Vec::new();
+        let mut newlines = 0;
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                self.bump();
+                comments.push(self.rest_of_comment().trim().to_owned());
+                newlines = 0;
+            } else if c == '\n' {
+                self.bump();
+                newlines += 1;
+                if newlines > 1 {
+                    comments.clear();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        comments
+    }
+
+    fn rest_of_comment(&mut self) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                self.skip_comment();
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            if !pred(c) {
+                break;
+            }
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+
+    fn rest_of_line(&mut self) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' || c == '#' {
+                break;
+            }
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.span();
+        self.bump();
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some(c) => out.push(c),
+                    None => break,
+                },
+                Some('\n') | None => break,
+                Some(c) => out.push(c),
+            }
+        }
+        Err(ParseError {
+            span: start,
+            message: "unterminated string".to_owned(),
+        })
+    }
+
+    fn document(mut self) -> Result<Document, ParseError> {
+        let mut doc = Document::default();
+
+        loop {
+            let comments = self.skip_whitespace_comments();
+            let span = self.span();
+            match self.peek() {
+                None => break,
+                Some(c) if is_ident(c) => {}
+                Some(c) => return self.error(format!("unexpected character '{}'", c)),
+            };
+
+            let ident = self.take_while(is_ident);
+            self.skip_inline_whitespace();
+
+            if ident == "task" && self.peek() != Some('=') {
+                let mut task = self.task(span)?;
+                if !comments.is_empty() {
+                    task.description = Some(comments.join(" "));
+                }
+                doc.tasks.push(task);
+            } else if self.peek() == Some('=') {
+                self.bump();
+                doc.variables.push(self.variable(ident, span)?);
+            } else {
+                return Err(ParseError {
+                    span,
+                    message: format!("expected 'task' or variable assignment, found '{}'", ident),
+                });
+            }
+        }
+
+        Ok(doc)
+    }
+
+    fn variable(&mut self, name: String, span: Span) -> Result<Variable, ParseError> {
+        self.skip_inline_whitespace();
+        let value_span = self.span();
+        let value = if self.peek() == Some('"') {
+            let value = self.quoted()?;
+            self.skip_inline_whitespace();
+            match self.peek() {
+                None | Some('\n') | Some('#') => {}
+                Some(_) => return self.error("unexpected input after value"),
+            }
+            value
+        } else {
+            self.rest_of_line().trim_end().to_owned()
+        };
+        check_interpolation(&value, value_span)?;
+        Ok(Variable { name, value, span })
+    }
+
+    /// A name is one or more words on a single line, or a quoted string.
+    /// Reading stops at `{`, `,` or a clause keyword.
+    fn name(&mut self) -> Result<String, ParseError> {
+        self.skip_inline_whitespace();
+        if self.peek() == Some('"') {
+            return self.quoted();
+        }
+
+        let mut words: Vec<String> = Vec::new();
+        loop {
+            self.skip_inline_whitespace();
+            match self.peek() {
+                Some(c) if is_word(c) => {}
+                _ => break,
+            }
+            if !words.is_empty() && self.clause().is_some() {
+                break;
+            }
+            words.push(self.take_while(is_word));
+        }
+
+        if words.is_empty() {
+            return self.error("expected task name");
+        }
+
+        Ok(words.join(" "))
+    }
+
+    /// Comma separated list of names.
+    fn names(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut names = Vec::new();
+        loop {
+            names.push(self.name()?);
+            self.skip_inline_whitespace();
+            if self.peek() == Some(',') {
+                self.bump();
+            } else {
+                return Ok(names);
+            }
+        }
+    }
+
+    /// Returns the clause starting at the current position, without consuming it.
+    fn clause(&self) -> Option<Clause> {
+        let mut lookahead = self.clone();
+        match lookahead.take_while(is_word).as_str() {
+            "inputs" => Some(Clause::Inputs),
+            "outputs" => Some(Clause::Outputs),
+            "depends" => {
+                lookahead.skip_inline_whitespace();
+                if lookahead.take_while(is_word) == "on" {
+                    Some(Clause::DependsOn)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn task(&mut self, span: Span) -> Result<TaskDecl, ParseError> {
+        let name = self.name()?;
+        let mut dependencies = Vec::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+
+        loop {
+            self.skip_inline_whitespace();
+            let clause = match self.clause() {
+                Some(clause) => clause,
+                None => break,
+            };
+            self.take_while(is_word);
+            if clause == Clause::DependsOn {
+                self.skip_inline_whitespace();
+                self.take_while(is_word);
+            }
+            let names = self.names()?;
+            match clause {
+                Clause::DependsOn => dependencies.extend(names),
+                Clause::Inputs => inputs.extend(names),
+                Clause::Outputs => outputs.extend(names),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.peek() != Some('{') {
+            return self.error("expected '{'");
+        }
+        self.bump();
+
+        let commands = self.body(span)?;
+
+        Ok(TaskDecl {
+            name,
+            description: None,
+            dependencies,
+            inputs,
+            outputs,
+            commands,
+            span,
+        })
+    }
+
+    /// Reads the task body up to the matching `}`. Every non-empty line is a command,
+    /// a trailing `\` continues the command on the next line.
+    fn body(&mut self, task: Span) -> Result<Vec<Command>, ParseError> {
+        let mut commands = Vec::new();
+        let mut depth = 0;
+        let mut quote = None;
+        let mut current = String::new();
+        let mut start = None;
+
+        loop {
+            let span = self.span();
+            let c = match self.bump() {
+                Some(c) => c,
+                None => {
+                    return Err(ParseError {
+                        span: task,
+                        message: "unterminated task body".to_owned(),
+                    })
+                }
+            };
+
+            // Braces and quotes in comment lines are not part of any command.
+            let comment = match start {
+                Some(_) => current.starts_with('#'),
+                None => c == '#',
+            };
+
+            match (c, quote) {
+                ('\n', _) => {
+                    if current.ends_with('\\') {
+                        current.pop();
+                        current.push(' ');
+                        continue;
+                    }
+                    quote = None;
+                    push_command(&mut commands, &mut current, &mut start)?;
+                    continue;
+                }
+                _ if comment => {}
+                ('}', None) if depth == 0 => {
+                    push_command(&mut commands, &mut current, &mut start)?;
+                    return Ok(commands);
+                }
+                ('{', None) => depth += 1,
+                ('}', None) => depth -= 1,
+                ('"', None) | ('\'', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                _ => {}
+            }
+
+            if start.is_none() {
+                if c.is_whitespace() {
+                    continue;
+                }
+                start = Some(span);
+            }
+            current.push(c);
+        }
+    }
+}
+
+fn push_command(
+    commands: &mut Vec<Command>,
+    current: &mut String,
+    start: &mut Option<Span>,
+) -> Result<(), ParseError> {
+    let line = std::mem::take(current);
+    let span = match start.take() {
+        Some(span) => span,
+        None => return Ok(()),
+    };
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(());
+    }
+    check_interpolation(line, span)?;
+    commands.push(Command {
+        line: line.to_owned(),
+        span,
+    });
+    Ok(())
+}
+
+fn check_interpolation(input: &str, span: Span) -> Result<(), ParseError> {
+    let mut column = span.column;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek() == Some(&'{') {
+            let start = column;
+            chars.next();
+            column += 1;
+            let mut closed = false;
+            for c in chars.by_ref() {
+                column += 1;
+                if c == '}' {
+                    closed = true;
+                    break;
+                }
+            }
+            if !closed {
+                return Err(ParseError {
+                    span: Span {
+                        line: span.line,
+                        column: start,
+                    },
+                    message: "unterminated variable".to_owned(),
+                });
+            }
+        }
+        column += 1;
+    }
+    Ok(())
+}
+
+/// Replace `$name` and `${name}` with the value of the variable.
+/// Unknown variables are left as is, so the shell can expand them, and `$$` is a literal `$`.
+pub(crate) fn interpolate(input: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('$') => {
+                chars.next();
+                out.push('$');
+            }
+            Some('{') => {
+                chars.next();
+                let name = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                match lookup(&name) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push_str("${");
+                        out.push_str(&name);
+                        out.push('}');
+                    }
+                }
+            }
+            Some(c) if is_ident(*c) => {
+                let mut name = String::new();
+                while let Some(c) = chars.peek() {
+                    if !is_ident(*c) {
+                        break;
+                    }
+                    name.push(*c);
+                    chars.next();
+                }
+                match lookup(&name) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push('$');
+                        out.push_str(&name);
+                    }
+                }
+            }
+            _ => out.push('$'),
+        }
+    }
+    out
+}
+
+pub fn parse(input: &str) -> Result<Document, ParseError> {
+    Parser::new(input).document()
+}
@@ -0,0 +1,230 @@
+use super::{Args, Error};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Content hashes of a task's inputs and outputs at the time it last succeeded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub inputs: String,
+    pub outputs: String,
+}
+
+impl Fingerprint {
+    /// Hash every file matched by the `inputs` and `outputs` globs.
+    ///
+    /// `args` are part of the inputs, so running a task with other arguments runs it again.
+    pub async fn compute(
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        args: &Args,
+    ) -> Result<Fingerprint, Error> {
+        let args = args
+            .iter()
+            .map(|(name, value)| format!("{}={}\0", name, value))
+            .collect::<String>();
+        runtime::spawn_blocking(move || {
+            Ok(Fingerprint {
+                inputs: hash_globs(&inputs, args.as_bytes())?,
+                outputs: hash_globs(&outputs, &[])?,
+            })
+        })
+        .await?
+    }
+}
+
+pub(crate) fn validate_globs(globs: &[String]) -> Result<(), Error> {
+    for pattern in globs {
+        if let Err(err) = glob::Pattern::new(pattern) {
+            return Err(Error::Pattern(format!("{}: {}", pattern, err)));
+        }
+    }
+    Ok(())
+}
+
+/// Sha256 over `prefix` and the path and content of every file matched by `globs`,
+/// in path order.
+fn hash_globs(globs: &[String], prefix: &[u8]) -> Result<String, Error> {
+    let mut paths = Vec::new();
+    for pattern in globs {
+        let entries = glob::glob(pattern).map_err(|err| Error::Pattern(err.to_string()))?;
+        for entry in entries {
+            let path = entry.map_err(std::io::Error::from)?;
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(prefix);
+    let mut buf = vec![0; 8 * 1024];
+    for path in paths {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        let mut file = fs::File::open(&path)?;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Replace the content of `path` by writing a temporary file next to it and renaming it,
+/// so an interrupted write leaves the previous content in place.
+pub(crate) fn write_file(path: &Path, content: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&tmp, content)?;
+    if let Err(err) = fs::rename(&tmp, path) {
+        fs::remove_file(&tmp).ok();
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Fingerprints of every task, keyed by task name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    tasks: HashMap<String, Fingerprint>,
+}
+
+impl Manifest {
+    /// Load a manifest. A missing or corrupt file gives an empty manifest,
+    /// so every task runs again.
+    pub fn load(path: impl AsRef<Path>) -> Result<Manifest, Error> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Manifest::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(serde_json::from_slice(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|err| Error::External(Box::new(err)))?;
+        write_file(path.as_ref(), &content)
+    }
+
+    pub fn get(&self, task: &str) -> Option<&Fingerprint> {
+        self.tasks.get(task)
+    }
+
+    pub fn insert(&mut self, task: impl Into<String>, fingerprint: Fingerprint) {
+        self.tasks.insert(task.into(), fingerprint);
+    }
+
+    pub fn remove(&mut self, task: &str) -> Option<Fingerprint> {
+        self.tasks.remove(task)
+    }
+}
+
+/// A manifest and the file it is persisted to.
+pub(crate) struct Store {
+    path: PathBuf,
+    manifest: Mutex<Manifest>,
+}
+
+impl Store {
+    pub fn open(path: PathBuf) -> Result<Store, Error> {
+        Ok(Store {
+            manifest: Mutex::new(Manifest::load(&path)?),
+            path,
+        })
+    }
+
+    pub fn is_fresh(&self, task: &str, fingerprint: &Fingerprint) -> bool {
+        self.manifest.lock().unwrap().get(task) == Some(fingerprint)
+    }
+
+    pub fn update(&self, task: &str, fingerprint: Fingerprint) {
+        self.manifest.lock().unwrap().insert(task, fingerprint);
+    }
+
+    pub fn remove(&self, task: &str) {
+        self.manifest.lock().unwrap().remove(task);
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.manifest.lock().unwrap().save(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Band, Error, Invocation, Manifest, Param, TaskBuilder, TaskContext};
+    use service::{service, Rejection};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_skip_up_to_date() {
+        let dir = std::env::temp_dir().join(format!("band-fingerprint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("input.txt"), "1").unwrap();
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let band = || {
+            let runs = runs.clone();
+            Band::new()
+                .add_task(
+                    "build",
+                    TaskBuilder::new(service!(move |ctx: TaskContext<()>| {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        async move { Result::<_, Rejection<_, Error>>::Ok((ctx, ())) }
+                    }))
+                    .input(dir.join("*.txt").to_string_lossy())
+                    .param(Param::flag("release")),
+                )
+                .manifest(dir.join("manifest.json"))
+                .build()
+                .unwrap()
+        };
+
+        runtime::block_on(band().run("build", ())).unwrap();
+        runtime::block_on(band().run("build", ())).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        std::fs::write(dir.join("input.txt"), "2").unwrap();
+        runtime::block_on(band().run("build", ())).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        let release = [Invocation::new("build").arg("release", true)];
+        runtime::block_on(band().run_with(&release, ())).unwrap();
+        runtime::block_on(band().run_with(&release, ())).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // A corrupt manifest runs every task again.
+        std::fs::write(dir.join("manifest.json"), "{\"tasks\":").unwrap();
+        runtime::block_on(band().run_with(&release, ())).unwrap();
+        runtime::block_on(band().run_with(&release, ())).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert!(Manifest::load(dir.join("manifest.json"))
+            .unwrap()
+            .get("build")
+            .is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
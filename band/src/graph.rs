@@ -0,0 +1,140 @@
+use super::{Band, Error, Param};
+use serde::Serialize;
+use slotmap::DefaultKey;
+use std::fmt::Write;
+
+/// A task in a [`Graph`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Node<'a> {
+    pub name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<&'a str>,
+    /// Direct dependencies of the task which are part of the graph.
+    pub dependencies: Vec<&'a str>,
+    pub inputs: &'a [String],
+    pub outputs: &'a [String],
+    pub params: &'a [Param],
+}
+
+/// The dependency graph of a band, or of a selection of its tasks.
+///
+/// Tasks are in dependency order, so every task comes after its dependencies.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Graph<'a> {
+    pub tasks: Vec<Node<'a>>,
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl<'a> Graph<'a> {
+    fn new<C>(band: &'a Band<C>, keys: &[DefaultKey]) -> Graph<'a> {
+        let tasks = keys
+            .iter()
+            .map(|key| {
+                let task = &band.tasks[*key];
+                Node {
+                    name: &task.name,
+                    description: task.description.as_deref(),
+                    dependencies: band.edges[*key]
+                        .iter()
+                        .filter(|dep| keys.contains(dep))
+                        .map(|dep| band.tasks[*dep].name.as_str())
+                        .collect(),
+                    inputs: &task.inputs,
+                    outputs: &task.outputs,
+                    params: &task.params,
+                }
+            })
+            .collect();
+        Graph { tasks }
+    }
+
+    /// Graphviz DOT, with an edge from every task to each of its dependencies.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph band {\n");
+        for task in &self.tasks {
+            match task.description {
+                Some(desc) => writeln!(out, "    {} [tooltip={}];", quote(task.name), quote(desc)),
+                None => writeln!(out, "    {};", quote(task.name)),
+            }
+            .unwrap();
+        }
+        for task in &self.tasks {
+            for dep in &task.dependencies {
+                writeln!(out, "    {} -> {};", quote(task.name), quote(dep)).unwrap();
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("graph is serializable")
+    }
+}
+
+impl<C> Band<C> {
+    /// Graph of every task in the band.
+    pub fn graph(&self) -> Graph<'_> {
+        let keys = self.all_tasks();
+        Graph::new(self, &keys)
+    }
+
+    /// Graph of `tasks` and their dependencies.
+    pub fn graph_for(&self, tasks: &[&str]) -> Result<Graph<'_>, Error> {
+        let keys = self.get_all_tasks(tasks)?;
+        Ok(Graph::new(self, &keys))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{exec, TaskBuilder};
+
+    #[test]
+    fn test_graph() {
+        let band = Band::<()>::new()
+            .add_task(
+                "build",
+                TaskBuilder::new(exec("true"))
+                    .description("Build \"all\"")
+                    .add_dependency("clean"),
+            )
+            .add_task("clean", TaskBuilder::new(exec("true")))
+            .add_task("lint", TaskBuilder::new(exec("true")))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            band.graph().to_dot(),
+            "digraph band {
+    \"clean\";
+    \"build\" [tooltip=\"Build \\\"all\\\"\"];
+    \"lint\";
+    \"build\" -> \"clean\";
+}
+"
+        );
+
+        let graph = band.graph_for(&["build"]).unwrap();
+        assert_eq!(
+            graph.to_json(),
+            serde_json::json!({
+                "tasks": [
+                    { "name": "clean", "dependencies": [], "inputs": [], "outputs": [], "params": [] },
+                    {
+                        "name": "build",
+                        "description": "Build \"all\"",
+                        "dependencies": ["clean"],
+                        "inputs": [],
+                        "outputs": [],
+                        "params": []
+                    }
+                ]
+            })
+        );
+    }
+}
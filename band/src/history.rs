@@ -0,0 +1,526 @@
+use super::events::{Event, Reporter, RunId, SkipReason, TaskId};
+use super::fingerprint::write_file;
+use super::{Band, Error};
+use serde::{Deserialize, Serialize};
+use slotmap::{DefaultKey, SecondaryMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Number of runs kept in a history. Older runs are dropped.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// Durations are stored as milliseconds.
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    /// Skipped because its inputs and outputs were unchanged.
+    UpToDate,
+    /// Skipped because a dependency failed or a condition did not hold.
+    Skipped,
+    Cancelled,
+}
+
+/// A task of a recorded run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub name: String,
+    pub outcome: Outcome,
+    #[serde(rename = "duration_ms", with = "millis")]
+    pub duration: Duration,
+}
+
+/// A recorded run, with its tasks in the order they finished.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Milliseconds since the unix epoch when the run started.
+    pub timestamp: u64,
+    #[serde(rename = "duration_ms", with = "millis")]
+    pub duration: Duration,
+    pub success: bool,
+    pub tasks: Vec<TaskRecord>,
+}
+
+/// Statistics of a task over the runs of a [`History`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskStats {
+    pub name: String,
+    /// Runs the task was part of, however it ended.
+    pub runs: usize,
+    pub failures: usize,
+    /// Runs the task was skipped as up to date.
+    pub cache_hits: usize,
+    /// Mean duration of the runs the task succeeded in.
+    pub mean: Duration,
+    pub max: Duration,
+}
+
+/// The slowest chain of dependent tasks, by their mean durations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalPath {
+    /// Tasks in the order they run.
+    pub tasks: Vec<String>,
+    pub duration: Duration,
+}
+
+/// The last [`HISTORY_LIMIT`] runs of a band.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    runs: Vec<RunRecord>,
+}
+
+impl History {
+    /// Load a history. A missing file gives an empty history.
+    pub fn load(path: impl AsRef<Path>) -> Result<History, Error> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(History::default()),
+            Err(err) => return Err(err.into()),
+        };
+        serde_json::from_slice(&content).map_err(|err| Error::External(Box::new(err)))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let content = serde_json::to_vec(self).map_err(|err| Error::External(Box::new(err)))?;
+        write_file(path.as_ref(), &content)
+    }
+
+    /// Recorded runs, oldest first.
+    pub fn runs(&self) -> &[RunRecord] {
+        &self.runs
+    }
+
+    pub fn record(&mut self, run: RunRecord) {
+        self.runs.push(run);
+        if self.runs.len() > HISTORY_LIMIT {
+            let excess = self.runs.len() - HISTORY_LIMIT;
+            self.runs.drain(..excess);
+        }
+    }
+
+    /// Statistics of every recorded task, slowest first.
+    pub fn stats(&self) -> Vec<TaskStats> {
+        let mut stats = BTreeMap::<&str, (TaskStats, Duration, u32)>::new();
+        for task in self.runs.iter().flat_map(|run| run.tasks.iter()) {
+            let (stats, total, succeeded) = stats.entry(&task.name).or_insert_with(|| {
+                let stats = TaskStats {
+                    name: task.name.clone(),
+                    runs: 0,
+                    failures: 0,
+                    cache_hits: 0,
+                    mean: Duration::ZERO,
+                    max: Duration::ZERO,
+                };
+                (stats, Duration::ZERO, 0)
+            });
+            stats.runs += 1;
+            match task.outcome {
+                Outcome::Succeeded => {
+                    *total += task.duration;
+                    *succeeded += 1;
+                    stats.max = stats.max.max(task.duration);
+                }
+                Outcome::Failed => stats.failures += 1,
+                Outcome::UpToDate => stats.cache_hits += 1,
+                Outcome::Skipped | Outcome::Cancelled => {}
+            }
+        }
+
+        let mut stats = stats
+            .into_values()
+            .map(|(mut stats, total, succeeded)| {
+                if succeeded > 0 {
+                    stats.mean = total / succeeded;
+                }
+                stats
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by_key(|s| std::cmp::Reverse(s.mean));
+        stats
+    }
+
+    /// Mean duration of `task` over the runs it succeeded in.
+    pub fn mean(&self, task: &str) -> Option<Duration> {
+        let durations = self
+            .runs
+            .iter()
+            .flat_map(|run| run.tasks.iter())
+            .filter(|t| t.name == task && t.outcome == Outcome::Succeeded)
+            .map(|t| t.duration)
+            .collect::<Vec<_>>();
+        if durations.is_empty() {
+            None
+        } else {
+            Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+        }
+    }
+
+    fn means(&self) -> HashMap<String, Duration> {
+        self.stats()
+            .into_iter()
+            .filter(|s| s.mean > Duration::ZERO)
+            .map(|s| (s.name, s.mean))
+            .collect()
+    }
+}
+
+/// A run being recorded.
+struct Current {
+    timestamp: u64,
+    started: HashMap<TaskId, Instant>,
+    tasks: Vec<TaskRecord>,
+}
+
+/// A history and the file it is persisted to, recording runs from their events.
+pub(crate) struct HistoryStore {
+    path: PathBuf,
+    history: Mutex<History>,
+    /// Runs being recorded. Runs of a band may overlap.
+    current: Mutex<HashMap<RunId, Current>>,
+    /// Why the history was not loaded, reported with the next run.
+    warning: Mutex<Option<String>>,
+}
+
+impl HistoryStore {
+    /// Open the history in `path`. A history which does not parse is replaced,
+    /// as it only feeds statistics.
+    pub fn open(path: PathBuf) -> Result<HistoryStore, Error> {
+        let (history, warning) = match History::load(&path) {
+            Ok(history) => (history, None),
+            Err(Error::Io(err)) => return Err(Error::Io(err)),
+            Err(err) => {
+                let warning = format!("ignoring the history in {}: {}", path.display(), err);
+                (History::default(), Some(warning))
+            }
+        };
+        Ok(HistoryStore {
+            history: Mutex::new(history),
+            current: Mutex::new(HashMap::new()),
+            warning: Mutex::new(warning),
+            path,
+        })
+    }
+
+    pub fn history(&self) -> History {
+        self.history.lock().unwrap().clone()
+    }
+
+    pub fn take_warning(&self) -> Option<String> {
+        self.warning.lock().unwrap().take()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.history.lock().unwrap().save(&self.path)
+    }
+}
+
+impl Reporter for HistoryStore {
+    fn report(&self, event: &Event) {
+        let id = match event.run() {
+            Some(id) => id,
+            None => return,
+        };
+        let mut current = self.current.lock().unwrap();
+        if let Event::RunStarted { .. } = event {
+            let run = Current {
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+                started: HashMap::new(),
+                tasks: Vec::new(),
+            };
+            current.insert(id, run);
+            return;
+        }
+        let run = match current.get_mut(&id) {
+            Some(run) => run,
+            None => return,
+        };
+
+        let (name, outcome, duration) = match event {
+            Event::TaskStarted { id, .. } => {
+                run.started.insert(*id, Instant::now());
+                return;
+            }
+            Event::TaskFinished { name, duration, .. } => (name, Outcome::Succeeded, *duration),
+            Event::TaskFailed { name, duration, .. } => (name, Outcome::Failed, *duration),
+            Event::TaskSkipped { name, reason, .. } => {
+                let outcome = match reason {
+                    SkipReason::UpToDate => Outcome::UpToDate,
+                    SkipReason::DependencyFailed | SkipReason::Condition => Outcome::Skipped,
+                };
+                (name, outcome, Duration::ZERO)
+            }
+            Event::TaskCancelled { id, name, .. } => {
+                let duration = run
+                    .started
+                    .get(id)
+                    .map(|start| start.elapsed())
+                    .unwrap_or_default();
+                (name, Outcome::Cancelled, duration)
+            }
+            Event::RunFinished {
+                duration, success, ..
+            } => {
+                let run = current.remove(&id).unwrap();
+                self.history.lock().unwrap().record(RunRecord {
+                    timestamp: run.timestamp,
+                    duration: *duration,
+                    success: *success,
+                    tasks: run.tasks,
+                });
+                return;
+            }
+            _ => return,
+        };
+        run.tasks.push(TaskRecord {
+            name: name.clone(),
+            outcome,
+            duration,
+        });
+    }
+}
+
+impl<C> Band<C> {
+    /// The recorded runs of the band, when it has a history.
+    /// See [`BandBuilder::history`](crate::BandBuilder::history).
+    pub fn history(&self) -> Option<History> {
+        self.history.as_ref().map(|store| store.history())
+    }
+
+    /// The critical path through `tasks` and their dependencies, or through all tasks
+    /// when `tasks` is empty. Tasks without recorded durations count as instant.
+    pub fn critical_path(&self, tasks: &[&str]) -> Result<CriticalPath, Error> {
+        let keys = if tasks.is_empty() {
+            self.all_tasks()
+        } else {
+            self.get_all_tasks(tasks)?
+        };
+        let history = self.history().unwrap_or_default();
+        Ok(self.critical_path_keys(&keys, &history.means()))
+    }
+
+    /// Statistics of `tasks` and their dependencies, or of every recorded task when
+    /// `tasks` is empty, slowest first.
+    pub fn stats(&self, tasks: &[&str]) -> Result<Vec<TaskStats>, Error> {
+        let stats = self.history().unwrap_or_default().stats();
+        if tasks.is_empty() {
+            return Ok(stats);
+        }
+        let names = self
+            .get_all_tasks(tasks)?
+            .into_iter()
+            .map(|key| self.tasks[key].name.as_str())
+            .collect::<HashSet<_>>();
+        Ok(stats
+            .into_iter()
+            .filter(|s| names.contains(s.name.as_str()))
+            .collect())
+    }
+
+    /// Expected duration of running `tasks`, which must be in dependency order.
+    pub(crate) fn estimate(&self, tasks: &[DefaultKey]) -> Option<Duration> {
+        let history = self.history.as_ref()?.history();
+        let means = history.means();
+        if means.is_empty() {
+            return None;
+        }
+        let total = tasks
+            .iter()
+            .filter_map(|key| means.get(self.tasks[*key].name.as_str()))
+            .sum::<Duration>();
+        let path = self.critical_path_keys(tasks, &means);
+        Some(path.duration.max(total / self.concurrency as u32))
+    }
+
+    fn critical_path_keys(
+        &self,
+        keys: &[DefaultKey],
+        means: &HashMap<String, Duration>,
+    ) -> CriticalPath {
+        // Finish time of every task when all of its dependencies finish as early as possible.
+        let mut finish = SecondaryMap::<DefaultKey, (Duration, Option<DefaultKey>)>::new();
+        for key in keys {
+            let task = &self.tasks[*key];
+            let (start, prev) = self.edges[*key]
+                .iter()
+                .filter_map(|dep| finish.get(*dep).map(|(end, _)| (*end, Some(*dep))))
+                .max_by_key(|(end, _)| *end)
+                .unwrap_or((Duration::ZERO, None));
+            let duration = means.get(task.name.as_str()).copied().unwrap_or_default();
+            finish.insert(*key, (start + duration, prev));
+        }
+
+        let mut last = keys.iter().max_by_key(|key| finish[**key].0).copied();
+        let duration = last.map(|key| finish[key].0).unwrap_or_default();
+        let mut tasks = Vec::new();
+        while let Some(key) = last {
+            tasks.push(self.tasks[key].name.clone());
+            last = finish[key].1;
+        }
+        tasks.reverse();
+        CriticalPath { tasks, duration }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{TaskBuilder, TaskContext};
+    use service::{service, Rejection};
+
+    fn sleep(
+        ms: u64,
+    ) -> impl service::Service<TaskContext<()>, Output = (TaskContext<()>, ()), Error = Error> {
+        service!(move |ctx: TaskContext<()>| async move {
+            runtime::sleep(Duration::from_millis(ms)).await;
+            Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+        })
+    }
+
+    #[test]
+    fn test_history() {
+        let dir = std::env::temp_dir().join(format!("band-history-{}", std::process::id()));
+        let path = dir.join("history.json");
+        let band = || {
+            Band::new()
+                .add_task("clean", TaskBuilder::new(sleep(1)))
+                .add_task("slow", TaskBuilder::new(sleep(40)).add_dependency("clean"))
+                .add_task("fast", TaskBuilder::new(sleep(1)).add_dependency("clean"))
+                .add_task(
+                    "all",
+                    TaskBuilder::new(sleep(1))
+                        .add_dependency("slow")
+                        .add_dependency("fast"),
+                )
+                .history(&path)
+                .build()
+                .unwrap()
+        };
+
+        let first = band();
+        assert_eq!(
+            first.estimate(&first.get_all_tasks(&["all"]).unwrap()),
+            None
+        );
+        runtime::block_on(first.run("all", ())).unwrap();
+
+        let band = band();
+        let history = band.history().unwrap();
+        assert_eq!(history.runs().len(), 1);
+        let run = &history.runs()[0];
+        assert!(run.success);
+        assert_eq!(run.tasks.len(), 4);
+        assert_eq!(run.tasks[0].name, "clean");
+        assert_eq!(run.tasks[3].name, "all");
+
+        let stats = history.stats();
+        assert_eq!(stats[0].name, "slow");
+        assert_eq!(stats[0].runs, 1);
+        assert!(stats[0].mean >= Duration::from_millis(40));
+
+        let mut names = band
+            .stats(&["fast"])
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["clean", "fast"]);
+
+        let path = band.critical_path(&[]).unwrap();
+        assert_eq!(path.tasks, vec!["clean", "slow", "all"]);
+        assert!(path.duration >= Duration::from_millis(42));
+        let estimate = band.estimate(&band.get_all_tasks(&["fast"]).unwrap());
+        assert!(estimate.unwrap() < Duration::from_millis(40));
+
+        let mut history = History::default();
+        for _ in 0..HISTORY_LIMIT + 5 {
+            history.record(run.clone());
+        }
+        assert_eq!(history.runs().len(), HISTORY_LIMIT);
+
+        // A history which cannot be saved does not fail the run.
+        let blocked = dir.join("blocked");
+        let warnings = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let recorded = warnings.clone();
+        let band = Band::new()
+            .add_task("fast", TaskBuilder::new(sleep(1)))
+            .history(blocked.join("history.json"))
+            .reporter(move |event: &Event| {
+                if let Event::Warning { message } = event {
+                    recorded.lock().unwrap().push(message.clone());
+                }
+            })
+            .build()
+            .unwrap();
+        std::fs::write(&blocked, "").unwrap();
+        runtime::block_on(band.run("fast", ())).unwrap();
+        assert_eq!(warnings.lock().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_overlapping_runs() {
+        let dir = std::env::temp_dir().join(format!("band-overlap-{}", std::process::id()));
+        let path = dir.join("history.json");
+        std::fs::create_dir_all(&dir).unwrap();
+        // Left behind by an interrupted save.
+        std::fs::write(&path, "{\"runs\":[").unwrap();
+
+        let warnings = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let recorded = warnings.clone();
+        let band = Band::new()
+            .add_task("slow", TaskBuilder::new(sleep(50)))
+            .add_task("fast", TaskBuilder::new(sleep(1)))
+            .history(&path)
+            .reporter(move |event: &Event| {
+                if let Event::Warning { message } = event {
+                    recorded.lock().unwrap().push(message.clone());
+                }
+            })
+            .build()
+            .map(std::sync::Arc::new)
+            .unwrap();
+
+        let slow = band.start(&["slow".into()], ());
+        let fast = band.start(&["fast".into()], ());
+        let (slow, fast) = runtime::block_on(futures::future::join(slow, fast));
+        slow.unwrap();
+        fast.unwrap();
+        assert_eq!(warnings.lock().unwrap().len(), 1);
+        assert!(warnings.lock().unwrap()[0].starts_with("ignoring the history"));
+
+        let history = History::load(&path).unwrap();
+        let tasks = history
+            .runs()
+            .iter()
+            .map(|run| {
+                let names = run.tasks.iter().map(|t| t.name.as_str());
+                names.collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tasks, vec![vec!["fast"], vec!["slow"]]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Action, Error};
+use futures_util::future::BoxFuture;
+use service::{Rejection, Service};
+use std::sync::Arc;
+
+/// The action of a task as seen by a middleware added with
+/// [`BandBuilder::wrap`](crate::BandBuilder::wrap).
+pub struct SharedAction<C>(Arc<Action<C>>);
+
+impl<C> Clone for SharedAction<C> {
+    fn clone(&self) -> Self {
+        SharedAction(self.0.clone())
+    }
+}
+
+impl<C> SharedAction<C> {
+    pub(crate) fn new(action: Action<C>) -> SharedAction<C> {
+        SharedAction(Arc::new(action))
+    }
+}
+
+impl<C> Service<C> for SharedAction<C> {
+    type Output = (C, ());
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<(C, ()), Rejection<C, Error>>>;
+
+    fn call(&self, ctx: C) -> Self::Future {
+        self.0.call(ctx)
+    }
+}
+
+pub(crate) type Wrapper<C> = Box<dyn Fn(Action<C>) -> Action<C>>;
+
+type FailureHook = Box<dyn Fn(&Error) + Send + Sync>;
+
+/// Actions run around every run of a band.
+pub(crate) struct Hooks<C> {
+    pub before: Vec<Action<C>>,
+    pub after: Vec<Action<C>>,
+    pub on_failure: Vec<FailureHook>,
+}
+
+impl<C> Default for Hooks<C> {
+    fn default() -> Self {
+        Hooks {
+            before: Vec::new(),
+            after: Vec::new(),
+            on_failure: Vec::new(),
+        }
+    }
+}
+
+/// Result of calling an action.
+pub(crate) fn action_result<C>(ret: Result<(C, ()), Rejection<C, Error>>) -> Result<(), Error> {
+    match ret {
+        Ok(_) => Ok(()),
+        Err(Rejection::Err(err)) => Err(err),
+        Err(Rejection::Reject(_, Some(err))) => Err(err),
+        Err(Rejection::Reject(_, None)) => Err(Error::Rejected),
+    }
+}
+
+impl<C> Hooks<C>
+where
+    C: Clone,
+{
+    pub async fn before(&self, ctx: &C) -> Result<(), Error> {
+        for action in &self.before {
+            action_result(action.call(ctx.clone()).await)?;
+        }
+        Ok(())
+    }
+
+    /// Run the after hooks, then the failure hooks if the run or a hook failed.
+    pub async fn after<T>(&self, ctx: &C, mut ret: Result<T, Error>) -> Result<T, Error> {
+        for action in &self.after {
+            let after = action_result(action.call(ctx.clone()).await);
+            if let (true, Err(err)) = (ret.is_ok(), after) {
+                ret = Err(err);
+            }
+        }
+        if let Err(err) = &ret {
+            self.failed(err);
+        }
+        ret
+    }
+
+    /// Run the failure hooks for a run which failed with `err`.
+    pub fn failed(&self, err: &Error) {
+        for hook in &self.on_failure {
+            hook(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{hook, task, Log};
+    use crate::{Band, TaskBuilder, TaskContext};
+    use futures::executor::block_on;
+    use service::MiddlewareFn;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_hooks() {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let failed = failures.clone();
+        let band = Band::new()
+            .add_task("a", TaskBuilder::new(task("a")))
+            .add_task("fail", TaskBuilder::new(task("fail")).add_dependency("a"))
+            .wrap(MiddlewareFn::<
+                TaskContext<Log>,
+                _,
+                SharedAction<TaskContext<Log>>,
+            >::new(
+                |next: SharedAction<TaskContext<Log>>, ctx: TaskContext<Log>| async move {
+                    ctx.lock().unwrap().push("enter");
+                    let (ctx, _) = match next.call(ctx).await {
+                        Ok(ret) => ret,
+                        Err(err) => return Err(err),
+                    };
+                    ctx.lock().unwrap().push("leave");
+                    Result::<_, Rejection<TaskContext<Log>, Error>>::Ok((ctx, ()))
+                },
+            ))
+            .before_run(hook("before"))
+            .after_run(hook("after"))
+            .on_failure(move |err| failed.lock().unwrap().push(err.to_string()))
+            .build()
+            .unwrap();
+
+        let log = Log::default();
+        block_on(band.run("a", log.clone())).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["before", "enter", "a", "leave", "after"]
+        );
+        assert!(failures.lock().unwrap().is_empty());
+
+        let log = Log::default();
+        block_on(band.run("fail", log.clone())).unwrap_err();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["before", "enter", "a", "leave", "enter", "fail", "after"]
+        );
+        assert_eq!(*failures.lock().unwrap(), vec!["task rejected"]);
+
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let failed = failures.clone();
+        let band = Band::new()
+            .add_task("a", TaskBuilder::new(task("a")))
+            .before_run(hook("fail"))
+            .after_run(hook("after"))
+            .on_failure(move |err| failed.lock().unwrap().push(err.to_string()))
+            .build()
+            .unwrap();
+
+        let log = Log::default();
+        block_on(band.run("a", log.clone())).unwrap_err();
+        assert_eq!(*log.lock().unwrap(), vec!["fail"]);
+        assert_eq!(*failures.lock().unwrap(), vec!["task rejected"]);
+    }
+}
@@ -1,4 +1,48 @@
+// Timers, blocking tasks and `block_on` of `runtime` panic without an executor.
+#[cfg(not(any(feature = "tokio", feature = "smol", feature = "async-std")))]
+compile_error!("band needs an executor: enable `tokio`, `smol` or `async-std`");
+
 mod band;
+mod cancel;
+pub mod cli;
+pub mod conditions;
+mod context;
 mod error;
+mod events;
+mod exec;
+pub mod file;
+mod fingerprint;
+mod graph;
+mod history;
+mod hooks;
+mod namespace;
+mod outputs;
+mod params;
+mod policy;
+mod scheduler;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use self::{
+    band::*,
+    cancel::{CancelHandle, Running},
+    context::TaskContext,
+    error::*,
+    events::{
+        ConsoleReporter, Event, JsonReporter, OutputStream, Reporter, RunId, SkipReason, TaskId,
+    },
+    exec::{exec, Output, ShellAction},
+    fingerprint::{Fingerprint, Manifest},
+    graph::{Graph, Node},
+    history::{CriticalPath, History, Outcome, RunRecord, TaskRecord, TaskStats, HISTORY_LIMIT},
+    hooks::SharedAction,
+    namespace::Namespace,
+    params::{Args, Dependency, Invocation, Param, ParamKind, Value},
+    policy::*,
+    scheduler::RunSummary,
+};
 
-pub use self::{band::*, error::*};
+#[cfg(feature = "watch")]
+pub use self::watch::DEFAULT_DEBOUNCE;
@@ -0,0 +1,134 @@
+use super::{Error, TaskBuilder, TaskContext, TaskDesc};
+use service::Service;
+use std::collections::{BTreeMap, HashSet};
+
+/// Separates the namespaces of a task name, as in `build:sass`.
+pub(crate) const SEPARATOR: char = ':';
+
+/// Builds the tasks of a namespace. See [`BandBuilder::namespace`](crate::BandBuilder::namespace).
+pub struct Namespace<C> {
+    prefix: String,
+    pub(crate) tasks: Vec<TaskDesc<C>>,
+}
+
+impl<C> Namespace<C> {
+    pub(crate) fn new(prefix: String) -> Namespace<C> {
+        Namespace {
+            prefix,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Full name of the task `name` in this namespace.
+    pub fn name(&self, name: &str) -> String {
+        format!("{}{}{}", self.prefix, SEPARATOR, name)
+    }
+
+    /// Add the task `<namespace>:<name>`. Dependencies are full task names.
+    pub fn add_task<A>(mut self, name: &str, builder: TaskBuilder<A, C>) -> Self
+    where
+        A: Service<TaskContext<C>, Output = (TaskContext<C>, ())> + Send + Sync + 'static,
+        A::Future: Send,
+        A::Error: Into<Error>,
+        C: 'static,
+    {
+        let name = self.name(name);
+        self.tasks.push(builder.build(name));
+        self
+    }
+
+    pub fn namespace<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(Namespace<C>) -> Namespace<C>,
+    {
+        let ns = f(Namespace::new(self.name(name)));
+        self.tasks.extend(ns.tasks);
+        self
+    }
+}
+
+/// Add a task for every namespace without a task of its own,
+/// depending on every task and nested namespace in it.
+pub(crate) fn add_aggregates<C>(tasks: &mut Vec<TaskDesc<C>>) {
+    let names = tasks.iter().map(|t| t.name.clone()).collect::<HashSet<_>>();
+
+    let mut members = BTreeMap::<String, Vec<String>>::new();
+    for name in &names {
+        let mut child = name.as_str();
+        while let Some(idx) = child.rfind(SEPARATOR) {
+            let parent = &child[..idx];
+            let entry = members.entry(parent.to_owned()).or_default();
+            if !entry.iter().any(|m| m == child) {
+                entry.push(child.to_owned());
+            }
+            child = parent;
+        }
+    }
+
+    for (namespace, mut members) in members {
+        if names.contains(&namespace) || namespace.is_empty() {
+            continue;
+        }
+        members.sort();
+        tasks.push(TaskDesc::group(
+            namespace.clone(),
+            format!("Run all tasks in {}", namespace),
+            members,
+        ));
+    }
+}
+
+/// Whether `name` selects tasks by a glob pattern rather than by name.
+pub(crate) fn is_pattern(name: &str) -> bool {
+    name.contains(&['*', '?', '['][..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{exec, Band};
+
+    #[test]
+    fn test_namespace() {
+        let band = Band::<()>::new()
+            .namespace("build", |ns| {
+                ns.add_task("sass", TaskBuilder::new(exec("true")))
+                    .namespace("js", |ns| {
+                        let min = ns.name("min");
+                        ns.add_task("min", TaskBuilder::new(exec("true")))
+                            .add_task("bundle", TaskBuilder::new(exec("true")).add_dependency(min))
+                    })
+            })
+            .add_task("clean", TaskBuilder::new(exec("true")))
+            .add_task("clean:sass", TaskBuilder::new(exec("true")))
+            .add_task("test[unit]", TaskBuilder::new(exec("true")))
+            .add_task("lint[", TaskBuilder::new(exec("true")))
+            .build()
+            .unwrap();
+
+        let info = band.tasks().find(|t| t.name == "build").unwrap();
+        assert_eq!(info.dependencies, vec!["build:js", "build:sass"]);
+        assert_eq!(info.description, Some("Run all tasks in build"));
+        assert_eq!(
+            band.get_tasks(&["build:js"]).unwrap(),
+            vec!["build:js:min", "build:js:bundle", "build:js"]
+        );
+        // `clean` is a task of its own, so it is not generated.
+        assert_eq!(
+            band.tasks()
+                .find(|t| t.name == "clean")
+                .unwrap()
+                .dependencies,
+            Vec::<&str>::new()
+        );
+
+        assert_eq!(
+            band.get_tasks(&["build:*"]).unwrap(),
+            vec!["build:sass", "build:js:min", "build:js:bundle", "build:js"]
+        );
+        assert!(band.get_tasks(&["test:*"]).is_none());
+        // Names which look like globs select the task of that name first.
+        assert_eq!(band.get_tasks(&["test[unit]"]).unwrap(), vec!["test[unit]"]);
+        assert_eq!(band.get_tasks(&["lint["]).unwrap(), vec!["lint["]);
+    }
+}
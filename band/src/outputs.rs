@@ -0,0 +1,148 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+type AnyMap = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// Outputs published by the tasks of a run, keyed by task and type.
+/// See [`TaskContext::publish`](crate::TaskContext::publish).
+#[derive(Default)]
+pub(crate) struct Outputs {
+    tasks: Mutex<HashMap<String, AnyMap>>,
+}
+
+impl Outputs {
+    pub fn insert<T: Send + Sync + 'static>(&self, task: &str, value: T) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .entry(task.to_owned())
+            .or_default()
+            .insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self, task: &str) -> Option<Arc<T>> {
+        let value = self
+            .tasks
+            .lock()
+            .unwrap()
+            .get(task)?
+            .get(&TypeId::of::<T>())?
+            .clone();
+        value.downcast().ok()
+    }
+
+    /// Whether `task` published any output.
+    pub fn contains(&self, task: &str) -> bool {
+        self.tasks.lock().unwrap().contains_key(task)
+    }
+
+    /// Drop the outputs of a failed attempt of `task`.
+    pub fn clear(&self, task: &str) {
+        self.tasks.lock().unwrap().remove(task);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Band, Error, TaskBuilder, TaskContext};
+    use futures::executor::block_on;
+    use service::{service, Rejection};
+
+    type Seen = Arc<Mutex<Vec<Option<Arc<u32>>>>>;
+
+    #[test]
+    fn test_outputs() {
+        let band = Band::new()
+            .add_task(
+                "ok",
+                TaskBuilder::new(service!(|ctx: TaskContext<Seen>| async move {
+                    ctx.publish(1u32);
+                    ctx.publish(2u32);
+                    Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+                })),
+            )
+            .add_task(
+                "fail",
+                TaskBuilder::new(service!(|ctx: TaskContext<Seen>| async move {
+                    ctx.publish(3u32);
+                    Result::<(TaskContext<Seen>, ()), _>::Err(Rejection::Err(Error::Rejected))
+                }))
+                .optional(),
+            )
+            .add_task(
+                "read",
+                TaskBuilder::new(service!(|ctx: TaskContext<Seen>| {
+                    ctx.lock().unwrap().extend(vec![
+                        ctx.output::<u32>("ok"),
+                        ctx.output::<u32>("fail"),
+                        ctx.output::<u32>("missing"),
+                    ]);
+                    assert!(ctx.output::<String>("ok").is_none());
+                    async move { Result::<_, Rejection<_, Error>>::Ok((ctx, ())) }
+                }))
+                .add_dependency("ok")
+                .add_dependency("fail"),
+            )
+            .build()
+            .unwrap();
+
+        let seen = Seen::default();
+        block_on(band.run("read", seen.clone())).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![Some(Arc::new(2)), None, None]);
+    }
+
+    #[test]
+    fn test_outputs_up_to_date() {
+        let dir = std::env::temp_dir().join(format!("band-outputs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("input.txt"), "1").unwrap();
+
+        let band = |publish: bool| {
+            Band::new()
+                .add_task(
+                    "compile",
+                    TaskBuilder::new(service!(move |ctx: TaskContext<Seen>| async move {
+                        if publish {
+                            ctx.publish(1u32);
+                        }
+                        Result::<_, Rejection<_, Error>>::Ok((ctx, ()))
+                    }))
+                    .input(dir.join("*.txt").to_string_lossy()),
+                )
+                .add_task(
+                    "package",
+                    TaskBuilder::new(service!(|ctx: TaskContext<Seen>| {
+                        ctx.lock().unwrap().push(ctx.output::<u32>("compile"));
+                        async move { Result::<_, Rejection<_, Error>>::Ok((ctx, ())) }
+                    }))
+                    .add_dependency("compile"),
+                )
+                .manifest(dir.join("manifest.json"))
+                .build()
+                .unwrap()
+        };
+
+        // A task publishing outputs runs every time, so its dependents see them.
+        let seen = Seen::default();
+        runtime::block_on(band(true).run("package", seen.clone())).unwrap();
+        runtime::block_on(band(true).run("package", seen.clone())).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![Some(Arc::new(1)); 2]);
+
+        // Other tasks are still skipped as up to date.
+        let seen = Seen::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for _ in 0..2 {
+            let mut band = band(false);
+            let recorded = events.clone();
+            band.add_reporter(move |event: &crate::Event| {
+                recorded.lock().unwrap().push(event.kind())
+            });
+            runtime::block_on(band.run("package", seen.clone())).unwrap();
+        }
+        assert!(events.lock().unwrap().contains(&"task_skipped"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
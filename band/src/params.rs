@@ -0,0 +1,390 @@
+use super::Error;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamKind::Bool => write!(f, "bool"),
+            ParamKind::Int => write!(f, "int"),
+            ParamKind::Float => write!(f, "float"),
+            ParamKind::String => write!(f, "string"),
+        }
+    }
+}
+
+/// The value of a task argument.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Value {
+    pub fn kind(&self) -> ParamKind {
+        match self {
+            Value::Bool(_) => ParamKind::Bool,
+            Value::Int(_) => ParamKind::Int,
+            Value::Float(_) => ParamKind::Float,
+            Value::String(_) => ParamKind::String,
+        }
+    }
+
+    /// Parse `input` as a value of `kind`.
+    pub fn parse(kind: ParamKind, input: &str) -> Option<Value> {
+        match kind {
+            ParamKind::Bool => match input {
+                "true" | "yes" | "1" => Some(Value::Bool(true)),
+                "false" | "no" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ParamKind::Int => input.parse().ok().map(Value::Int),
+            ParamKind::Float => input.parse().ok().map(Value::Float),
+            ParamKind::String => Some(Value::String(input.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Float(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+/// A named parameter declared by a task.
+///
+/// Parameters without a default must be given when the task runs,
+/// except flags which default to `false`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Param {
+    pub fn new(name: impl Into<String>, kind: ParamKind) -> Param {
+        Param {
+            name: name.into(),
+            kind,
+            default: None,
+            description: None,
+        }
+    }
+
+    /// A boolean parameter defaulting to `false`, given as `--name` on the command line.
+    pub fn flag(name: impl Into<String>) -> Param {
+        Param::new(name, ParamKind::Bool).default(false)
+    }
+
+    pub fn string(name: impl Into<String>) -> Param {
+        Param::new(name, ParamKind::String)
+    }
+
+    pub fn int(name: impl Into<String>) -> Param {
+        Param::new(name, ParamKind::Int)
+    }
+
+    pub fn float(name: impl Into<String>) -> Param {
+        Param::new(name, ParamKind::Float)
+    }
+
+    /// Panics if the type of `value` does not match the parameter.
+    pub fn default(mut self, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        assert_eq!(
+            value.kind(),
+            self.kind,
+            "default of parameter '{}' has the wrong type",
+            self.name
+        );
+        self.default = Some(value);
+        self
+    }
+
+    pub fn description(mut self, description: impl ToString) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+}
+
+/// Validated arguments of a running task. See [`TaskContext::args`](crate::TaskContext::args).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    values: BTreeMap<String, Value>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// `false` when the argument is missing or not a bool.
+    pub fn get_bool(&self, name: &str) -> bool {
+        matches!(self.get(name), Some(Value::Bool(true)))
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(Value::Int(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        match self.get(name) {
+            Some(Value::Float(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Check `raw` against `params`, filling in defaults.
+    pub(crate) fn validate(
+        task: &str,
+        params: &[Param],
+        raw: &[(String, String)],
+    ) -> Result<Args, Error> {
+        let error = |message: String| Error::Argument {
+            task: task.to_owned(),
+            message,
+        };
+
+        let mut values = BTreeMap::new();
+        for (name, input) in raw {
+            let param = match params.iter().find(|p| &p.name == name) {
+                Some(param) => param,
+                None => return Err(error(format!("unknown parameter '{}'", name))),
+            };
+            let value = match Value::parse(param.kind, input) {
+                Some(value) => value,
+                None => {
+                    return Err(error(format!(
+                        "expected {} for '{}', got '{}'",
+                        param.kind, name, input
+                    )))
+                }
+            };
+            match values.get(name) {
+                Some(prev) if *prev != value => {
+                    return Err(error(format!(
+                        "conflicting values for '{}': '{}' and '{}'",
+                        name, prev, value
+                    )))
+                }
+                _ => {
+                    values.insert(name.clone(), value);
+                }
+            }
+        }
+
+        for param in params {
+            if values.contains_key(&param.name) {
+                continue;
+            }
+            match &param.default {
+                Some(value) => {
+                    values.insert(param.name.clone(), value.clone());
+                }
+                None => return Err(error(format!("missing argument '{}'", param.name))),
+            }
+        }
+
+        Ok(Args { values })
+    }
+}
+
+/// Where a dependency gets the value of an argument from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ArgSource {
+    Fixed(String),
+    /// An argument of the dependent task with the given name.
+    Forward(String),
+}
+
+/// A dependency of a task, with the arguments it is run with.
+///
+/// ```
+/// # use band::Dependency;
+/// // Run `build:sass` minified, and in release mode when the dependent task is.
+/// let dep = Dependency::new("build:sass")
+///     .arg("minify", "true")
+///     .forward("release");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub(crate) name: String,
+    pub(crate) args: Vec<(String, ArgSource)>,
+}
+
+impl Dependency {
+    pub fn new(name: impl ToString) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Always run the dependency with `name` set to `value`.
+    pub fn arg(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.args
+            .push((name.into(), ArgSource::Fixed(value.to_string())));
+        self
+    }
+
+    /// Pass the argument `name` of the dependent task on to the dependency.
+    pub fn forward(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.forward_as(name.clone(), name)
+    }
+
+    /// Pass the argument `from` of the dependent task on as `name`.
+    pub fn forward_as(mut self, name: impl Into<String>, from: impl Into<String>) -> Self {
+        self.args
+            .push((name.into(), ArgSource::Forward(from.into())));
+        self
+    }
+}
+
+/// A task to run together with its raw arguments, like `build --release --target=x`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub task: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl Invocation {
+    pub fn new(task: impl Into<String>) -> Invocation {
+        Invocation {
+            task: task.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.args.push((name.into(), value.to_string()));
+        self
+    }
+}
+
+impl From<&str> for Invocation {
+    fn from(task: &str) -> Self {
+        Invocation::new(task)
+    }
+}
+
+impl From<String> for Invocation {
+    fn from(task: String) -> Self {
+        Invocation::new(task)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let params = vec![
+            Param::flag("release"),
+            Param::string("target"),
+            Param::int("level").default(2i64),
+        ];
+        let raw = |args: &[(&str, &str)]| {
+            args.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let args = Args::validate(
+            "build",
+            &params,
+            &raw(&[("release", "true"), ("target", "x")]),
+        )
+        .unwrap();
+        assert!(args.get_bool("release"));
+        assert_eq!(args.get_str("target"), Some("x"));
+        assert_eq!(args.get_int("level"), Some(2));
+
+        let err = Args::validate("build", &params, &[]).unwrap_err();
+        assert_eq!(err.to_string(), "task 'build': missing argument 'target'");
+
+        let err = Args::validate("build", &params, &raw(&[("level", "high")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "task 'build': expected int for 'level', got 'high'"
+        );
+
+        let err = Args::validate("build", &params, &raw(&[("target", "x"), ("target", "y")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "task 'build': conflicting values for 'target': 'x' and 'y'"
+        );
+    }
+}
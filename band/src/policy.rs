@@ -0,0 +1,13 @@
+/// What happens to a run when a task fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// Stop the run at the first failure, cancelling running tasks.
+    #[default]
+    FailFast,
+    /// Keep running every task which does not depend on a failed task.
+    KeepGoing,
+    /// Report the failure, but treat the task as done so dependents still run.
+    Optional,
+}
+
+pub use service::Backoff;
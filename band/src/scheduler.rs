@@ -0,0 +1,349 @@
+use super::{
+    context::{TaskContext, TaskEnv},
+    events::{Event, RunId, SkipReason, TaskId},
+    fingerprint::Fingerprint,
+    hooks::action_result,
+    outputs::Outputs,
+    params::Args,
+    Band, CancelHandle, Error, Failure, FailurePolicy,
+};
+use futures_util::{
+    future::{self, Either, FutureExt},
+    stream::{FuturesUnordered, StreamExt},
+};
+use service::{Policy, Rejection};
+use slotmap::{DefaultKey, SecondaryMap};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Run `selected` as a dag.
+///
+/// `selected` must be in dependency order and every task is started as soon as
+/// the dependencies which are part of the selection are done.
+/// At most `band.concurrency` tasks are running at the same time.
+///
+/// How a failing task affects the rest of the run is decided by its failure policy,
+/// falling back to the failure policy of the band. Failures of optional tasks are
+/// returned as warnings.
+///
+/// When `cancel` fires, the running tasks are dropped and the run fails with
+/// [`Error::Cancelled`].
+pub(crate) async fn run<C>(
+    band: &Band<C>,
+    run: RunId,
+    selected: &[DefaultKey],
+    args: &SecondaryMap<DefaultKey, Arc<Args>>,
+    cancel: &CancelHandle,
+    ctx: C,
+) -> Result<RunSummary, Error>
+where
+    C: Clone,
+{
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let in_selection = selected.iter().copied().collect::<HashSet<_>>();
+
+    let mut pending = HashMap::with_capacity(selected.len());
+    let mut dependents = HashMap::<DefaultKey, Vec<DefaultKey>>::new();
+    let mut ready = VecDeque::new();
+
+    for key in selected {
+        band.emit(Event::TaskQueued {
+            run,
+            id: TaskId::from(*key),
+            name: band.tasks[*key].name.clone(),
+        });
+
+        let deps = band.edges[*key]
+            .iter()
+            .filter(|dep| in_selection.contains(dep))
+            .collect::<Vec<_>>();
+
+        if deps.is_empty() {
+            ready.push_back(*key);
+        } else {
+            pending.insert(*key, deps.len());
+        }
+
+        for dep in deps {
+            dependents.entry(*dep).or_default().push(*key);
+        }
+    }
+
+    let reporters = band.reporters.clone().into();
+    let outputs = Arc::new(Outputs::default());
+    let mut running = FuturesUnordered::new();
+    let mut in_flight = Vec::new();
+    let mut failures = Vec::new();
+    let mut warnings = Vec::new();
+
+    loop {
+        while running.len() < band.concurrency.max(1) {
+            let key = match ready.pop_front() {
+                Some(key) => key,
+                None => break,
+            };
+            let env = Arc::new(TaskEnv {
+                run,
+                id: TaskId::from(key),
+                name: band.tasks[key].name.clone(),
+                args: args[key].clone(),
+                outputs: Arc::clone(&outputs),
+                reporters: Arc::clone(&reporters),
+            });
+            let future = run_task(band, key, TaskContext::new(ctx.clone(), env));
+            running.push(future.map(move |ret| (key, ret)));
+            in_flight.push(key);
+        }
+
+        let (key, ret) = match future::select(running.next(), cancel.cancelled()).await {
+            Either::Left((Some(next), _)) => next,
+            Either::Left((None, _)) => break,
+            Either::Right(_) => {
+                drop(running);
+                cancelled(band, run, &in_flight);
+                return Err(Error::Cancelled);
+            }
+        };
+        in_flight.retain(|k| *k != key);
+
+        if let Err(error) = ret {
+            let task = &band.tasks[key];
+            match task.failure_policy.unwrap_or(band.failure_policy) {
+                FailurePolicy::FailFast => {
+                    drop(running);
+                    cancelled(band, run, &in_flight);
+                    return Err(error);
+                }
+                FailurePolicy::Optional => warnings.push(Failure {
+                    task: task.name.clone(),
+                    error,
+                }),
+                FailurePolicy::KeepGoing => {
+                    block_dependents(band, run, key, &mut dependents, &mut pending);
+                    failures.push(Failure {
+                        task: task.name.clone(),
+                        error,
+                    });
+                    continue;
+                }
+            }
+        }
+
+        for dependent in dependents.remove(&key).unwrap_or_default() {
+            // Missing when the dependent was skipped because of a failed dependency.
+            let count = match pending.get_mut(&dependent) {
+                Some(count) => count,
+                None => continue,
+            };
+            *count -= 1;
+            if *count == 0 {
+                pending.remove(&dependent);
+                ready.push_back(dependent);
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(RunSummary { warnings })
+    } else {
+        Err(Error::Failed(failures))
+    }
+}
+
+/// Outcome of a run which succeeded.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    /// Failures of optional tasks, which did not fail the run.
+    pub warnings: Vec<Failure>,
+}
+
+/// Report the dropped tasks `keys` as cancelled.
+fn cancelled<C>(band: &Band<C>, run: RunId, keys: &[DefaultKey]) {
+    for key in keys {
+        band.emit(Event::TaskCancelled {
+            run,
+            id: TaskId::from(*key),
+            name: band.tasks[*key].name.clone(),
+        });
+    }
+}
+
+/// Skip every task downstream of the failed task `key`.
+fn block_dependents<C>(
+    band: &Band<C>,
+    run: RunId,
+    key: DefaultKey,
+    dependents: &mut HashMap<DefaultKey, Vec<DefaultKey>>,
+    pending: &mut HashMap<DefaultKey, usize>,
+) {
+    let mut queue = dependents.remove(&key).unwrap_or_default();
+    while let Some(dependent) = queue.pop() {
+        if pending.remove(&dependent).is_none() {
+            continue;
+        }
+        band.emit(Event::TaskSkipped {
+            run,
+            id: TaskId::from(dependent),
+            name: band.tasks[dependent].name.clone(),
+            reason: SkipReason::DependencyFailed,
+        });
+        queue.extend(dependents.remove(&dependent).unwrap_or_default());
+    }
+}
+
+/// Run a single task, skipping it when a condition does not hold or its
+/// fingerprint is unchanged.
+///
+/// Outputs are not persisted, so the fingerprint of a task which published any is
+/// dropped rather than stored, and the task runs again next time.
+async fn run_task<C>(band: &Band<C>, key: DefaultKey, ctx: TaskContext<C>) -> Result<(), Error>
+where
+    C: Clone,
+{
+    let task = &band.tasks[key];
+    let run = ctx.env().run;
+    let id = TaskId::from(key);
+    let fail = |duration: Duration, error: Error| {
+        band.emit(Event::TaskFailed {
+            run,
+            id,
+            name: task.name.clone(),
+            duration,
+            error: error.to_string(),
+        });
+        error
+    };
+
+    for condition in &task.conditions {
+        if !condition(ctx.clone()).await {
+            band.emit(Event::TaskSkipped {
+                run,
+                id,
+                name: task.name.clone(),
+                reason: SkipReason::Condition,
+            });
+            return Ok(());
+        }
+    }
+
+    let store = match &band.store {
+        Some(store) if !task.inputs.is_empty() => Some(store),
+        _ => None,
+    };
+
+    let before = match store {
+        Some(store) => {
+            let inputs = task.inputs.clone();
+            let fingerprint =
+                match Fingerprint::compute(inputs, task.outputs.clone(), ctx.args()).await {
+                    Ok(fingerprint) => fingerprint,
+                    Err(err) => return Err(fail(Duration::ZERO, err)),
+                };
+            if store.is_fresh(&task.name, &fingerprint) {
+                band.emit(Event::TaskSkipped {
+                    run,
+                    id,
+                    name: task.name.clone(),
+                    reason: SkipReason::UpToDate,
+                });
+                return Ok(());
+            }
+            Some(fingerprint)
+        }
+        None => None,
+    };
+
+    band.emit(Event::TaskStarted {
+        run,
+        id,
+        name: task.name.clone(),
+    });
+    let start = Instant::now();
+
+    let action = match &task.action {
+        Some(action) => action,
+        None => {
+            band.emit(Event::TaskFinished {
+                run,
+                id,
+                name: task.name.clone(),
+                duration: start.elapsed(),
+            });
+            return Ok(());
+        }
+    };
+
+    let outputs = &ctx.env().outputs;
+    let mut attempt = 1;
+    let ret = loop {
+        let action = action.call(ctx.clone());
+        let ret = match task.timeout {
+            Some(timeout) => {
+                let sleep = Box::pin(runtime::sleep(timeout));
+                match future::select(action, sleep).await {
+                    Either::Left((ret, _)) => ret,
+                    Either::Right(_) => Err(Rejection::Err(Error::Timeout(timeout))),
+                }
+            }
+            None => action.await,
+        };
+        let ret = action_result(ret);
+
+        let err = match ret {
+            Ok(()) => break Ok(()),
+            Err(err) => err,
+        };
+        // Rejections are failures of the task too, so they are all retried.
+        let delay = match task.retry.retry(attempt, &Rejection::<(), _>::Err(&err)) {
+            Some(delay) => delay,
+            None => break Err(err),
+        };
+        outputs.clear(&task.name);
+        attempt += 1;
+        band.emit(Event::TaskRetrying {
+            run,
+            id,
+            name: task.name.clone(),
+            attempt,
+            error: err.to_string(),
+        });
+        if delay > Duration::from_secs(0) {
+            runtime::sleep(delay).await;
+        }
+    };
+
+    if let Err(err) = ret {
+        outputs.clear(&task.name);
+        return Err(fail(start.elapsed(), err));
+    }
+
+    if let (Some(store), true) = (store, outputs.contains(&task.name)) {
+        store.remove(&task.name);
+    } else if let (Some(store), Some(before)) = (store, before) {
+        let after =
+            match Fingerprint::compute(Vec::new(), task.outputs.clone(), &Args::default()).await {
+                Ok(after) => after,
+                Err(err) => return Err(fail(start.elapsed(), err)),
+            };
+        store.update(
+            &task.name,
+            Fingerprint {
+                inputs: before.inputs,
+                outputs: after.outputs,
+            },
+        );
+    }
+
+    band.emit(Event::TaskFinished {
+        run,
+        id,
+        name: task.name.clone(),
+        duration: start.elapsed(),
+    });
+
+    Ok(())
+}
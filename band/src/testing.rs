@@ -0,0 +1,47 @@
+//! Fixtures shared by the tests of the crate.
+
+use super::{Error, TaskContext};
+use service::{service, Rejection, Service};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Context recording what ran, in order.
+pub type Log = Arc<Mutex<Vec<&'static str>>>;
+
+/// A task logging `name`, which fails when it is named `fail`.
+pub fn task(
+    name: &'static str,
+) -> impl Service<TaskContext<Log>, Output = (TaskContext<Log>, ()), Error = Error> {
+    flaky(name, if name == "fail" { usize::MAX } else { 0 })
+}
+
+/// A task logging `name`, which fails its first `fails` attempts.
+pub fn flaky(
+    name: &'static str,
+    fails: usize,
+) -> impl Service<TaskContext<Log>, Output = (TaskContext<Log>, ()), Error = Error> {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    service!(move |ctx: TaskContext<Log>| {
+        let attempts = attempts.clone();
+        async move {
+            ctx.lock().unwrap().push(name);
+            if attempts.fetch_add(1, Ordering::SeqCst) < fails {
+                Err(Rejection::Err(Error::Rejected))
+            } else {
+                Ok((ctx, ()))
+            }
+        }
+    })
+}
+
+/// A run hook logging `name`, which fails when it is named `fail`.
+pub fn hook(name: &'static str) -> impl Service<Log, Output = (Log, ()), Error = Error> {
+    service!(move |ctx: Log| async move {
+        ctx.lock().unwrap().push(name);
+        if name == "fail" {
+            Err(Rejection::Err(Error::Rejected))
+        } else {
+            Ok((ctx, ()))
+        }
+    })
+}
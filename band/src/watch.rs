@@ -0,0 +1,347 @@
+use super::{Args, Band, CancelHandle, Error, Invocation, RunSummary};
+use futures_channel::mpsc;
+use futures_util::{
+    future::{self, BoxFuture, Either, FutureExt},
+    stream::{self, Stream, StreamExt},
+};
+use notify::{event::ModifyKind, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use slotmap::{DefaultKey, SecondaryMap};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time to wait for a burst of filesystem events to settle before running.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Directory to watch for a glob: the components before the first wildcard.
+fn base_dir(pattern: &Path) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in pattern.components() {
+        if let Component::Normal(part) = component {
+            if part.to_string_lossy().contains(&['*', '?', '[', '{'][..]) {
+                break;
+            }
+        }
+        base.push(component);
+    }
+    while !base.exists() {
+        if !base.pop() {
+            break;
+        }
+    }
+    base
+}
+
+fn absolute(pattern: &str) -> Result<PathBuf, Error> {
+    let path = Path::new(pattern);
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+struct State<'a, C> {
+    band: &'a Band<C>,
+    ctx: C,
+    /// Selected tasks in dependency order.
+    selected: Vec<DefaultKey>,
+    args: SecondaryMap<DefaultKey, Arc<Args>>,
+    /// Tasks, and the tasks downstream of them, to run for a change matching the patterns.
+    triggers: Vec<(Vec<glob::Pattern>, Vec<DefaultKey>)>,
+    /// Output patterns of the selected tasks.
+    outputs: SecondaryMap<DefaultKey, Vec<glob::Pattern>>,
+    /// Tasks of the current or last run.
+    running: Vec<DefaultKey>,
+    events: mpsc::UnboundedReceiver<PathBuf>,
+    affected: HashSet<DefaultKey>,
+    _watcher: RecommendedWatcher,
+}
+
+impl<'a, C> State<'a, C>
+where
+    C: Clone,
+{
+    /// Mark the tasks affected by a change to `path`. Returns true if any were.
+    ///
+    /// Outputs of the tasks of the current or last run are written by the run itself,
+    /// so changes to them are ignored.
+    fn affect(&mut self, path: &Path) -> bool {
+        let written = self.running.iter().any(|key| {
+            let mut patterns = self.outputs.get(*key).into_iter().flatten();
+            patterns.any(|p| p.matches_path(path))
+        });
+        if written {
+            return false;
+        }
+
+        let mut affected = false;
+        for (patterns, keys) in &self.triggers {
+            if patterns.iter().any(|p| p.matches_path(path)) {
+                self.affected.extend(keys.iter().copied());
+                affected = true;
+            }
+        }
+        affected
+    }
+
+    /// Wait for changes and run the affected tasks.
+    /// Changes arriving while the tasks are running cancel the run once they settle,
+    /// and it is restarted with the union of the affected tasks.
+    async fn next(&mut self) -> Option<Result<RunSummary, Error>> {
+        loop {
+            while self.affected.is_empty() {
+                let path = self.events.next().await?;
+                self.affect(&path);
+            }
+
+            loop {
+                let sleep = Box::pin(runtime::sleep(self.band.debounce));
+                match future::select(self.events.next(), sleep).await {
+                    Either::Left((Some(path), _)) => {
+                        self.affect(&path);
+                    }
+                    Either::Left((None, _)) => return None,
+                    Either::Right(_) => break,
+                }
+            }
+
+            let keys = self
+                .selected
+                .iter()
+                .filter(|key| self.affected.contains(key))
+                .copied()
+                .collect::<Vec<_>>();
+            self.affected.clear();
+            self.running = keys.clone();
+
+            let band = self.band;
+            let args = self.args.clone();
+            let cancel = CancelHandle::new();
+            let mut run = Box::pin(band.run_keys(&keys, &args, &cancel, self.ctx.clone()));
+            // Runs out once the changes made during the run settle.
+            let mut settled: Option<BoxFuture<'static, ()>> = None;
+
+            loop {
+                let changes = future::select(
+                    self.events.next(),
+                    match settled.as_mut() {
+                        Some(sleep) => Either::Left(sleep),
+                        None => Either::Right(future::pending()),
+                    },
+                );
+                match future::select(run.as_mut(), changes).await {
+                    // Tasks affected during the run are run next.
+                    Either::Left((ret, _)) => return Some(ret),
+                    Either::Right((Either::Left((Some(path), _)), _)) => {
+                        if self.affect(&path) {
+                            settled = Some(runtime::sleep(band.debounce).boxed());
+                        }
+                    }
+                    Either::Right((Either::Left((None, _)), _)) => return None,
+                    Either::Right((Either::Right(_), _)) => {
+                        // Let the run report its cancelled tasks before restarting.
+                        cancel.cancel();
+                        run.await.ok();
+                        self.affected.extend(keys);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<C> Band<C>
+where
+    C: Clone,
+{
+    /// Set the time to wait for filesystem changes to settle in [`Band::watch`].
+    pub fn set_debounce(&mut self, duration: Duration) {
+        self.debounce = duration;
+    }
+
+    /// Run `tasks`, then watch the inputs of `tasks` and their dependencies.
+    ///
+    /// When an input changes, the tasks reading it and every selected task downstream
+    /// of them are run again. The stream yields the result of every run, starting
+    /// with the initial run of all selected tasks.
+    pub fn watch<'a>(
+        &'a self,
+        tasks: &[&str],
+        ctx: C,
+    ) -> Result<impl Stream<Item = Result<RunSummary, Error>> + 'a, Error> {
+        let invocations = tasks
+            .iter()
+            .map(|task| Invocation::new(*task))
+            .collect::<Vec<_>>();
+        self.watch_with(&invocations, ctx)
+    }
+
+    /// Like [`Band::watch`], passing arguments to the tasks.
+    pub fn watch_with<'a>(
+        &'a self,
+        invocations: &[Invocation],
+        ctx: C,
+    ) -> Result<impl Stream<Item = Result<RunSummary, Error>> + 'a, Error> {
+        let names = invocations
+            .iter()
+            .map(|i| i.task.as_str())
+            .collect::<Vec<_>>();
+        let selected = self.get_all_tasks(&names)?;
+        let args = self.resolve_args(&selected, invocations)?;
+        let in_selection = selected.iter().copied().collect::<HashSet<_>>();
+
+        let mut dependents = HashMap::<DefaultKey, Vec<DefaultKey>>::new();
+        for key in &selected {
+            for dep in self.edges[*key].iter() {
+                dependents.entry(*dep).or_default().push(*key);
+            }
+        }
+
+        let (sender, events) = mpsc::unbounded();
+        let mut watcher = notify::recommended_watcher(move |ret: notify::Result<notify::Event>| {
+            let event = match ret {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            match event.kind {
+                EventKind::Create(_) | EventKind::Remove(_) => {}
+                EventKind::Modify(ModifyKind::Metadata(_)) => return,
+                EventKind::Modify(_) => {}
+                _ => return,
+            }
+            for path in event.paths {
+                sender.unbounded_send(path).ok();
+            }
+        })
+        .map_err(|err| Error::External(Box::new(err)))?;
+
+        let pattern = |glob: &str| {
+            glob::Pattern::new(&absolute(glob)?.to_string_lossy())
+                .map_err(|err| Error::Pattern(format!("{}: {}", glob, err)))
+        };
+        let mut watched = HashSet::new();
+        let mut triggers = Vec::new();
+        let mut outputs = SecondaryMap::new();
+        for key in &selected {
+            let task = &self.tasks[*key];
+            let patterns = task.outputs.iter().map(|output| pattern(output));
+            outputs.insert(*key, patterns.collect::<Result<Vec<_>, _>>()?);
+            if task.inputs.is_empty() {
+                continue;
+            }
+
+            let mut patterns = Vec::with_capacity(task.inputs.len());
+            for input in &task.inputs {
+                patterns.push(pattern(input)?);
+
+                let base = base_dir(&absolute(input)?);
+                if watched.insert(base.clone()) {
+                    watcher
+                        .watch(&base, RecursiveMode::Recursive)
+                        .map_err(|err| Error::External(Box::new(err)))?;
+                }
+            }
+
+            let mut downstream = vec![*key];
+            let mut idx = 0;
+            while idx < downstream.len() {
+                for dependent in dependents.get(&downstream[idx]).into_iter().flatten() {
+                    if in_selection.contains(dependent) && !downstream.contains(dependent) {
+                        downstream.push(*dependent);
+                    }
+                }
+                idx += 1;
+            }
+
+            triggers.push((patterns, downstream));
+        }
+
+        let state = State {
+            band: self,
+            ctx,
+            affected: selected.iter().copied().collect(),
+            selected,
+            args,
+            triggers,
+            outputs,
+            running: Vec::new(),
+            events,
+            _watcher: watcher,
+        };
+
+        let stream = stream::unfold(state, |mut state| async move {
+            let ret = state.next().await?;
+            Some((ret, state))
+        });
+
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{TaskBuilder, TaskContext};
+    use service::{service, Rejection};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_base_dir() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(base_dir(&cwd.join("src/**/*.rs")), cwd.join("src"));
+        assert_eq!(base_dir(&cwd.join("missing/dir/*.rs")), cwd);
+    }
+
+    #[test]
+    fn test_watch() {
+        let dir = std::env::temp_dir().join(format!("band-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("input.txt"), "1").unwrap();
+
+        // The output of the task matches its own inputs.
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let output = dir.join("output.txt");
+        let mut band = Band::new()
+            .add_task(
+                "build",
+                TaskBuilder::new(service!(move |ctx: TaskContext<()>| {
+                    let run = counted.fetch_add(1, Ordering::SeqCst);
+                    std::fs::write(&output, run.to_string()).unwrap();
+                    async move { Result::<_, Rejection<_, Error>>::Ok((ctx, ())) }
+                }))
+                .input(dir.join("*.txt").to_string_lossy())
+                .output(dir.join("output.txt").to_string_lossy()),
+            )
+            .build()
+            .unwrap();
+        band.set_debounce(Duration::from_millis(50));
+
+        runtime::block_on(async {
+            let mut watch = Box::pin(band.watch(&["build"], ()).unwrap());
+            let settle = || runtime::sleep(Duration::from_millis(500)).boxed();
+
+            watch.next().await.unwrap().unwrap();
+            assert_eq!(runs.load(Ordering::SeqCst), 1);
+            // Writing its output does not run the task again.
+            assert!(matches!(
+                future::select(watch.next(), settle()).await,
+                Either::Right(_)
+            ));
+
+            std::fs::write(dir.join("input.txt"), "2").unwrap();
+            watch.next().await.unwrap().unwrap();
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
+            assert!(matches!(
+                future::select(watch.next(), settle()).await,
+                Either::Right(_)
+            ));
+        });
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,12 +2,12 @@ use std::error::Error as StdError;
 use std::fmt;
 #[derive(Debug)]
 pub struct SpawnError {
-    pub(crate) inner: Box<dyn StdError>,
+    pub(crate) inner: Box<dyn StdError + Send + Sync>,
 }
 
 impl fmt::Display for SpawnError {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(())
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner)
     }
 }
 
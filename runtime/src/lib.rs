@@ -29,6 +29,19 @@ mod tokio_impl {
             })
     }
 
+    pub fn block_on<T: Future>(future: T) -> T::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build tokio runtime")
+            .block_on(future)
+    }
+
+    #[cfg(feature = "time")]
+    pub async fn sleep(duration: std::time::Duration) {
+        tokio::time::sleep(duration).await
+    }
+
     // #[cfg(feature = "time")]
     // pub async fn interval(duration: std::time::Duration) -> impl futures_core::Stream<Item = ()> {
     //     use futures_util::StreamExt;
@@ -56,6 +69,15 @@ mod smol_impl {
     {
         Ok(smol::unblock(task).await)
     }
+
+    pub fn block_on<T: Future>(future: T) -> T::Output {
+        smol::block_on(future)
+    }
+
+    #[cfg(feature = "time")]
+    pub async fn sleep(duration: std::time::Duration) {
+        smol::Timer::after(duration).await;
+    }
 }
 
 #[cfg(feature = "async-std")]
@@ -68,7 +90,7 @@ mod async_impl {
         T: Future + Send + 'static,
         T::Output: Send + 'static,
     {
-        async_std::task::spawn(future).await
+        Ok(async_std::task::spawn(future).await)
     }
 
     pub async fn spawn_blocking<F, R>(task: F) -> Result<R, SpawnError>
@@ -76,13 +98,20 @@ mod async_impl {
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
-        async_std::task::spawn_blocking(task).await
+        Ok(async_std::task::spawn_blocking(task).await)
+    }
+
+    pub fn block_on<T: Future>(future: T) -> T::Output {
+        async_std::task::block_on(future)
     }
 
     #[cfg(feature = "time")]
-    pub async fn interval(
-        duration: std::time::Duration,
-    ) -> impl futures_core::Stream<Item = std::time::Instant> {
+    pub async fn sleep(duration: std::time::Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    #[cfg(feature = "time")]
+    pub async fn interval(duration: std::time::Duration) -> impl futures_core::Stream<Item = ()> {
         async_std::stream::interval(duration)
     }
 }
@@ -93,7 +122,7 @@ pub use tokio_impl::*;
 #[cfg(feature = "smol")]
 pub use smol_impl::*;
 
-#[cfg(feature = "async")]
+#[cfg(feature = "async-std")]
 pub use async_impl::*;
 
 #[cfg(all(
@@ -121,6 +150,17 @@ mod default {
     {
         panic!("no runtime specified. enable one of features: tokio, smol, async")
     }
+
+    #[allow(unused)]
+    pub fn block_on<T: Future>(future: T) -> T::Output {
+        panic!("no runtime specified. enable one of features: tokio, smol, async")
+    }
+
+    #[allow(unused)]
+    #[cfg(feature = "time")]
+    pub async fn sleep(duration: std::time::Duration) {
+        panic!("no runtime specified. enable one of features: tokio, smol, async")
+    }
 }
 
 #[cfg(all(
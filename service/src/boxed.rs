@@ -70,51 +70,31 @@ impl<'a, I, O, E> Clone for BoxService<'a, I, O, E> {
     }
 }
 
-// pub trait DynamicMiddleware<'a, S, I, O, E>:
-//     Middleware<I, S, Service = BoxService<'a, I, O, E>> + Send + Sync
-// where
-//     S: Service<I, Output = O, Error = E, Future = BoxFuture<'a, Result<O, Rejection<I, E>>>>,
-// {
-//     fn box_clone(&self) -> BoxMiddleware<'a, S, I, O, E>;
-// }
-
-// pub type BoxMiddleware<'a, S, I, O, E> = Box<dyn DynamicMiddleware<'a, S, I, O, E> + 'a>;
-
-// impl<'a, S, I, O, E> Middleware<I, S> for BoxMiddleware<'a, S, I, O, E>
-// where
-//     I: 'a,
-//     S: 'a + Service<I, Output = O, Error = E, Future = BoxFuture<'a, Result<O, Rejection<I, E>>>>,
-//     S::Future: 'a,
-//     E: 'a,
-//     O: 'a,
-// {
-//     type Service = BoxService<'a, I, O, E>;
-//     fn wrap(&self, req: S) -> Self::Service {
-//         box_service::<'a, I, O, E, _>(self.as_ref().wrap(req))
-//     }
-// }
+/// A middleware wrapping boxed services, so middlewares of different types
+/// can be kept in one list. See [`MiddlewareStack`](crate::vec::MiddlewareStack).
+pub trait DynamicMiddleware<'a, I, O, E>: Send + Sync {
+    fn wrap_boxed(&self, service: BoxService<'a, I, O, E>) -> BoxService<'a, I, O, E>;
+}
 
-// impl<'a, S, I, O, E> Clone for BoxMiddleware<'a, S, I, O, E>
-// where
-//     S: Service<I, Output = O, Error = E, Future = BoxFuture<'a, Result<O, Rejection<I, E>>>>,
-// {
-//     fn clone(&self) -> Self {
-//         self.as_ref().box_clone()
-//     }
-// }
+pub type BoxMiddleware<'a, I, O, E> = Arc<dyn DynamicMiddleware<'a, I, O, E> + 'a>;
 
-// struct BoxedMiddleware<'a, M>(M, PhantomData<&'a ()>);
+impl<'a, M, I, O, E> DynamicMiddleware<'a, I, O, E> for M
+where
+    M: Middleware<I, BoxService<'a, I, O, E>> + Send + Sync,
+    M::Service: Service<I, Output = O, Error = E> + Clone + Send + Sync + 'a,
+    <M::Service as Service<I>>::Future: 'a,
+{
+    fn wrap_boxed(&self, service: BoxService<'a, I, O, E>) -> BoxService<'a, I, O, E> {
+        box_service(self.wrap(service))
+    }
+}
 
-// impl<'a, M, R, T> Middleware<R, T> for BoxedMiddleware<'a, M>
-// where
-//     T: Service<R>,
-//     T::Future: 'a + Send,
-// {
-//     type Service = BoxService<'a, R, T::Output, T::Error>;
-//     fn wrap(&self, req: R) -> Self::Service {
-//         Box::pin(self.0.call(req))
-//     }
-// }
+pub fn box_middleware<'a, I, O, E, M>(middleware: M) -> BoxMiddleware<'a, I, O, E>
+where
+    M: DynamicMiddleware<'a, I, O, E> + 'a,
+{
+    Arc::new(middleware)
+}
 
 // pub struct BoxOrBuilder<I, O, E> {
 //     task: Vec<BoxService<I, O, E>>,
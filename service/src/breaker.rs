@@ -0,0 +1,385 @@
+use crate::{Middleware, Rejection, Service};
+use alloc::{collections::VecDeque, sync::Arc};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::ready;
+use pin_project::pin_project;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Error of a call rejected by an open [`CircuitBreaker`], without calling the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circuit breaker is open")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through.
+    Closed,
+    /// Calls are rejected until the cool-down is over.
+    Open,
+    /// The cool-down is over: one call at a time goes through as a probe, closing the
+    /// circuit when it succeeds and opening it again when it fails.
+    HalfOpen,
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitState::Closed => write!(f, "closed"),
+            CircuitState::Open => write!(f, "open"),
+            CircuitState::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+enum State {
+    Closed,
+    Open(Instant),
+    HalfOpen { probing: bool },
+}
+
+struct Circuit {
+    state: State,
+    /// Whether each of the last calls failed, the oldest first.
+    outcomes: VecDeque<bool>,
+}
+
+/// Outcome of a call through the breaker.
+enum Call {
+    Success,
+    Failure,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    failure_rate: f64,
+    window: usize,
+    min_calls: usize,
+    cool_down: Duration,
+}
+
+/// Stops calling a failing service for a while.
+///
+/// The breaker tracks whether the last `window` calls failed with [`Rejection::Err`].
+/// Once at least `min_calls` of them are known and the rate of failures reaches
+/// `failure_rate`, the circuit opens: calls are rejected right away with
+/// [`CircuitOpen`], converted into the error of the wrapped service. Being rejections,
+/// an [`or_else`](crate::ServiceExt::or_else) can answer them instead. After the
+/// cool-down the circuit is half-open and lets a single call probe the service.
+///
+/// The state is shared by every service wrapped by the same `CircuitBreaker` and its clones.
+///
+/// ```
+/// # use service::{pass, CircuitBreaker, CircuitOpen, CircuitState, ServiceExt};
+/// # use std::time::Duration;
+/// # #[derive(Debug)]
+/// # struct Error;
+/// # impl From<CircuitOpen> for Error {
+/// #     fn from(_: CircuitOpen) -> Error { Error }
+/// # }
+/// let breaker = CircuitBreaker::new(0.5, 20).cool_down(Duration::from_secs(10));
+/// let backend = pass::<(), Error>().with(breaker.clone());
+/// assert_eq!(breaker.state(), CircuitState::Closed);
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: Config,
+    circuit: Arc<Mutex<Circuit>>,
+}
+
+impl CircuitBreaker {
+    /// Open when `failure_rate`, between 0 and 1, of the last `window` calls failed.
+    pub fn new(failure_rate: f64, window: usize) -> CircuitBreaker {
+        let window = window.max(1);
+        CircuitBreaker {
+            config: Config {
+                failure_rate: failure_rate.clamp(0.0, 1.0),
+                window,
+                min_calls: window,
+                cool_down: Duration::from_secs(30),
+            },
+            circuit: Arc::new(Mutex::new(Circuit {
+                state: State::Closed,
+                outcomes: VecDeque::with_capacity(window),
+            })),
+        }
+    }
+
+    /// Calls needed before the circuit can open, the whole window by default.
+    pub fn min_calls(mut self, calls: usize) -> Self {
+        self.config.min_calls = calls.clamp(1, self.config.window);
+        self
+    }
+
+    /// Time the circuit stays open before probing the service, 30 seconds by default.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.config.cool_down = cool_down;
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match self.circuit.lock().unwrap().state {
+            State::Closed => CircuitState::Closed,
+            State::Open(since) if since.elapsed() < self.config.cool_down => CircuitState::Open,
+            State::Open(_) | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Rate of failures of the calls in the window, 0 when there are none.
+    pub fn failure_rate(&self) -> f64 {
+        let circuit = self.circuit.lock().unwrap();
+        match circuit.outcomes.len() {
+            0 => 0.0,
+            n => circuit.outcomes.iter().filter(|failed| **failed).count() as f64 / n as f64,
+        }
+    }
+
+    /// Whether a call may go through, and whether it probes a half-open circuit.
+    fn acquire(&self) -> Option<bool> {
+        let mut circuit = self.circuit.lock().unwrap();
+        match circuit.state {
+            State::Closed => Some(false),
+            State::Open(since) if since.elapsed() < self.config.cool_down => None,
+            State::Open(_) | State::HalfOpen { probing: false } => {
+                circuit.state = State::HalfOpen { probing: true };
+                Some(true)
+            }
+            State::HalfOpen { probing: true } => None,
+        }
+    }
+
+    fn record(&self, probe: bool, call: Call) {
+        let mut circuit = self.circuit.lock().unwrap();
+        if probe {
+            circuit.state = match call {
+                Call::Success => State::Closed,
+                Call::Failure => State::Open(Instant::now()),
+                Call::Rejected => State::HalfOpen { probing: false },
+            };
+            circuit.outcomes.clear();
+            return;
+        }
+
+        let failed = match call {
+            Call::Success => false,
+            Call::Failure => true,
+            Call::Rejected => return,
+        };
+        // Calls started before the circuit opened do not count anymore.
+        if !matches!(circuit.state, State::Closed) {
+            return;
+        }
+        if circuit.outcomes.len() == self.config.window {
+            circuit.outcomes.pop_front();
+        }
+        circuit.outcomes.push_back(failed);
+
+        let calls = circuit.outcomes.len();
+        let failures = circuit.outcomes.iter().filter(|failed| **failed).count();
+        if calls >= self.config.min_calls
+            && failures > 0
+            && failures as f64 >= self.config.failure_rate * calls as f64
+        {
+            circuit.state = State::Open(Instant::now());
+            circuit.outcomes.clear();
+        }
+    }
+}
+
+impl<R, T> Middleware<R, T> for CircuitBreaker
+where
+    T: Service<R>,
+    T::Error: From<CircuitOpen>,
+    CircuitFuture<T::Future, R>: Send,
+{
+    type Service = CircuitBreakerService<T>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        CircuitBreakerService {
+            service,
+            breaker: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CircuitBreakerService<T> {
+    service: T,
+    breaker: CircuitBreaker,
+}
+
+impl<R, T> Service<R> for CircuitBreakerService<T>
+where
+    T: Service<R>,
+    T::Error: From<CircuitOpen>,
+    CircuitFuture<T::Future, R>: Send,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Future = CircuitFuture<T::Future, R>;
+
+    fn call(&self, req: R) -> Self::Future {
+        match self.breaker.acquire() {
+            Some(probe) => CircuitFuture {
+                state: CircuitFutureState::Call {
+                    fut: self.service.call(req),
+                    guard: Guard {
+                        breaker: Some(self.breaker.clone()),
+                        probe,
+                    },
+                },
+            },
+            None => CircuitFuture {
+                state: CircuitFutureState::Open(Some(req)),
+            },
+        }
+    }
+}
+
+/// Releases the probe of a half-open circuit when a call is dropped before finishing.
+struct Guard {
+    breaker: Option<CircuitBreaker>,
+    probe: bool,
+}
+
+impl Guard {
+    fn record(&mut self, call: Call) {
+        if let Some(breaker) = self.breaker.take() {
+            breaker.record(self.probe, call);
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.record(Call::Rejected);
+    }
+}
+
+#[pin_project(project = CircuitFutureStateProj)]
+enum CircuitFutureState<F, R> {
+    Call {
+        #[pin]
+        fut: F,
+        guard: Guard,
+    },
+    Open(Option<R>),
+}
+
+#[pin_project]
+pub struct CircuitFuture<F, R> {
+    #[pin]
+    state: CircuitFutureState<F, R>,
+}
+
+impl<F, O, R, E> Future for CircuitFuture<F, R>
+where
+    F: Future<Output = Result<O, Rejection<R, E>>>,
+    E: From<CircuitOpen>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            CircuitFutureStateProj::Call { fut, guard } => {
+                let ret = ready!(fut.poll(cx));
+                guard.record(match &ret {
+                    Ok(_) => Call::Success,
+                    Err(Rejection::Err(_)) => Call::Failure,
+                    Err(Rejection::Reject(..)) => Call::Rejected,
+                });
+                Poll::Ready(ret)
+            }
+            CircuitFutureStateProj::Open(req) => {
+                let req = req.take().expect("poll after done");
+                Poll::Ready(Err(Rejection::Reject(req, Some(CircuitOpen.into()))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::ready;
+    use crate::{service, ServiceExt};
+    use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Backend,
+        Open,
+    }
+
+    impl From<CircuitOpen> for Error {
+        fn from(_: CircuitOpen) -> Self {
+            Error::Open
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let failing = Arc::new(AtomicBool::new(true));
+        let (c, f) = (calls.clone(), failing.clone());
+        let backend = service!(move |req: u32| {
+            c.fetch_add(1, Ordering::SeqCst);
+            let fail = f.load(Ordering::SeqCst);
+            async move {
+                if fail {
+                    Err(Rejection::Err(Error::Backend))
+                } else {
+                    Ok(req)
+                }
+            }
+        });
+        let breaker = CircuitBreaker::new(0.5, 4).cool_down(Duration::from_millis(20));
+        let service = backend.with(breaker.clone());
+
+        assert!(ready(service.call(1)).is_err());
+        ready(service.call(1)).ok();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.failure_rate(), 1.0);
+        ready(service.call(1)).ok();
+        ready(service.call(1)).ok();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Open: rejected without calling the backend, so a stand-in can answer.
+        match ready(service.call(5)) {
+            Err(Rejection::Reject(5, Some(Error::Open))) => {}
+            _ => panic!("expected the circuit to be open"),
+        }
+        let stand_in =
+            service!(|req: u32| async move { Result::<_, Rejection<u32, Error>>::Ok(req) });
+        let fallback = service.clone().or_else(stand_in).unify();
+        assert_eq!(ready(fallback.call(6)).ok(), Some(6));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // A failed probe opens the circuit again.
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(ready(service.call(1)).is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // A successful probe closes it.
+        std::thread::sleep(Duration::from_millis(30));
+        failing.store(false, Ordering::SeqCst);
+        assert_eq!(ready(service.call(7)).ok(), Some(7));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+    }
+}
@@ -0,0 +1,430 @@
+use crate::{BoxFuture, Middleware, Rejection, Service};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
+use core::future;
+use core::hash::Hash;
+use core::task::{Poll, Waker};
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Takes the key a request is cached under.
+pub trait CacheKey<R> {
+    type Key;
+    fn key(&self, req: &R) -> Self::Key;
+}
+
+/// Caches requests under themselves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByRequest;
+
+impl<R: Clone> CacheKey<R> for ByRequest {
+    type Key = R;
+
+    fn key(&self, req: &R) -> R {
+        req.clone()
+    }
+}
+
+impl<R, K, F> CacheKey<R> for F
+where
+    F: Fn(&R) -> K,
+{
+    type Key = K;
+
+    fn key(&self, req: &R) -> K {
+        (self)(req)
+    }
+}
+
+struct Entry<O> {
+    output: O,
+    created: Instant,
+    used: u64,
+}
+
+struct State<K, O> {
+    entries: HashMap<K, Entry<O>>,
+    /// Keys by last use, the least recently used first.
+    order: BTreeMap<u64, K>,
+    /// Keys being computed, with the calls waiting for them by id.
+    pending: HashMap<K, Vec<(u64, Waker)>>,
+    /// Id of the next waiting call.
+    waiter: u64,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, O: Clone> State<K, O> {
+    fn get(&mut self, key: &K, ttl: Option<Duration>) -> Option<O> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => ttl.is_some_and(|ttl| entry.created.elapsed() >= ttl),
+            None => return None,
+        };
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key).unwrap();
+        self.order.remove(&entry.used);
+        self.order.insert(tick, key.clone());
+        entry.used = tick;
+        Some(entry.output.clone())
+    }
+
+    fn insert(&mut self, key: K, output: O, capacity: Option<usize>) {
+        self.remove(&key);
+        if let Some(capacity) = capacity {
+            while self.entries.len() >= capacity {
+                match self.order.pop_first() {
+                    Some((_, oldest)) => self.entries.remove(&oldest),
+                    None => break,
+                };
+            }
+            if capacity == 0 {
+                return;
+            }
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                output,
+                created: Instant::now(),
+                used: self.tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+}
+
+/// Marks a key as being computed until dropped, then wakes the calls waiting for it,
+/// also when the call computing it was cancelled.
+struct Pending<K: Hash + Eq, O> {
+    key: K,
+    state: Arc<Mutex<State<K, O>>>,
+}
+
+impl<K: Hash + Eq, O> Drop for Pending<K, O> {
+    fn drop(&mut self) {
+        let waiters = self.state.lock().unwrap().pending.remove(&self.key);
+        for (_, waker) in waiters.into_iter().flatten() {
+            waker.wake();
+        }
+    }
+}
+
+/// Caches the outputs of a service by request.
+///
+/// Requests are keyed by themselves with [`Cache::new`], or by a function with
+/// [`Cache::keyed`]. Outputs can expire after a time to live, and the least recently
+/// used are evicted over the capacity. Concurrent calls with the same key share one
+/// call of the wrapped service: the first computes the output, the others wait for it
+/// and only call the service themselves when it failed. Failures are never cached.
+///
+/// The cache is shared by every service wrapped by the same `Cache` and its clones.
+///
+/// ```
+/// # use service::{Cache, Rejection, Service, ServiceExt};
+/// # use std::time::Duration;
+/// let lookup = service::service!(|id: u32| async move {
+///     Result::<_, Rejection<u32, ()>>::Ok(id * 2)
+/// })
+/// .with(Cache::new().ttl(Duration::from_secs(60)).capacity(1000));
+/// ```
+pub struct Cache<K, O, F = ByRequest> {
+    key: Arc<F>,
+    ttl: Option<Duration>,
+    capacity: Option<usize>,
+    state: Arc<Mutex<State<K, O>>>,
+}
+
+impl<K, O> Cache<K, O> {
+    pub fn new() -> Cache<K, O> {
+        Cache::keyed(ByRequest)
+    }
+}
+
+impl<K, O> Default for Cache<K, O> {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
+impl<K, O, F> Cache<K, O, F> {
+    /// Cache requests under the key returned by `key`.
+    pub fn keyed(key: F) -> Cache<K, O, F> {
+        Cache {
+            key: Arc::new(key),
+            ttl: None,
+            capacity: None,
+            state: Arc::new(Mutex::new(State {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                pending: HashMap::new(),
+                waiter: 0,
+                tick: 0,
+            })),
+        }
+    }
+
+    /// Expire outputs `ttl` after they were computed.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep at most `capacity` outputs, evicting the least recently used.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Number of cached outputs, including expired ones not evicted yet.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.order.clear();
+    }
+}
+
+impl<K: Hash + Eq + Clone, O: Clone, F> Cache<K, O, F> {
+    pub fn remove(&self, key: &K) {
+        self.state.lock().unwrap().remove(key);
+    }
+}
+
+impl<K, O, F> Clone for Cache<K, O, F> {
+    fn clone(&self) -> Self {
+        Cache {
+            key: self.key.clone(),
+            ttl: self.ttl,
+            capacity: self.capacity,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<R, T, K, F> Middleware<R, T> for Cache<K, T::Output, F>
+where
+    T: Service<R> + Send + Sync + 'static,
+    T::Output: Clone + Send + 'static,
+    F: CacheKey<R, Key = K> + Send + Sync + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+    R: Send + 'static,
+{
+    type Service = CacheService<T, K, T::Output, F>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        CacheService {
+            service: Arc::new(service),
+            cache: self.clone(),
+        }
+    }
+}
+
+pub struct CacheService<T, K, O, F> {
+    service: Arc<T>,
+    cache: Cache<K, O, F>,
+}
+
+impl<T, K, O, F> Clone for CacheService<T, K, O, F> {
+    fn clone(&self) -> Self {
+        CacheService {
+            service: self.service.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<R, T, K, F> Service<R> for CacheService<T, K, T::Output, F>
+where
+    T: Service<R> + Send + Sync + 'static,
+    T::Output: Clone + Send + 'static,
+    F: CacheKey<R, Key = K> + Send + Sync + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+    R: Send + 'static,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    #[allow(clippy::type_complexity)]
+    type Future = BoxFuture<'static, Result<Self::Output, Rejection<R, Self::Error>>>;
+
+    fn call(&self, req: R) -> Self::Future {
+        let service = self.service.clone();
+        let cache = self.cache.clone();
+        let key = cache.key.key(&req);
+        Box::pin(async move {
+            let _pending = loop {
+                {
+                    let mut state = cache.state.lock().unwrap();
+                    if let Some(output) = state.get(&key, cache.ttl) {
+                        return Ok(output);
+                    }
+                    if !state.pending.contains_key(&key) {
+                        state.pending.insert(key.clone(), Vec::new());
+                        break Pending {
+                            key: key.clone(),
+                            state: cache.state.clone(),
+                        };
+                    }
+                }
+                // Another call computes the output, look again when it is done.
+                let (state, key) = (cache.state.clone(), key.clone());
+                let mut id = None;
+                future::poll_fn(move |cx| {
+                    let mut state = state.lock().unwrap();
+                    let next = state.waiter;
+                    let waiters = match state.pending.get_mut(&key) {
+                        Some(waiters) => waiters,
+                        None => return Poll::Ready(()),
+                    };
+                    // Polled again before the output is done: replace its waker.
+                    match waiters.iter_mut().find(|(i, _)| Some(*i) == id) {
+                        Some((_, waker)) => waker.clone_from(cx.waker()),
+                        None => {
+                            waiters.push((next, cx.waker().clone()));
+                            state.waiter += 1;
+                            id = Some(next);
+                        }
+                    }
+                    Poll::Pending
+                })
+                .await;
+            };
+
+            let output = service.call(req).await?;
+            cache
+                .state
+                .lock()
+                .unwrap()
+                .insert(key, output.clone(), cache.capacity);
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{block_on, ready, Delay};
+    use crate::{service, ServiceExt};
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use core::task::Context;
+
+    fn counted(
+        calls: Arc<AtomicU32>,
+    ) -> impl Service<u32, Output = u32, Error = ()> + Send + Sync + 'static {
+        service!(move |req: u32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                Delay::new(3).await;
+                match req {
+                    0 => Err(Rejection::Err(())),
+                    _ => Ok(req * 2),
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_cache() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let cache = Cache::new().capacity(2);
+        let service = counted(calls.clone()).with(cache.clone());
+
+        for req in [1, 2, 1, 3, 1, 2] {
+            assert_eq!(block_on(service.call(req)).ok(), Some(req * 2));
+        }
+        // 2 was evicted by 3, as 1 was used more recently.
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.len(), 2);
+
+        // Failures are not cached.
+        assert!(block_on(service.call(0)).is_err());
+        assert!(block_on(service.call(0)).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        cache.remove(&1);
+        assert_eq!(block_on(service.call(1)).ok(), Some(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn test_cache_coalesce() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let service = counted(calls.clone()).with(Cache::keyed(|req: &u32| req % 10));
+        let both = service.clone().join(service.clone());
+        assert_eq!(block_on(both.call(4)).ok(), Some((8, 8)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // Keyed by the last digit.
+        assert_eq!(block_on(service.call(14)).ok(), Some(8));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // A failed call lets the waiting one try itself.
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut first = service.call(0);
+        let mut second = service.call(10);
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+        assert!(block_on(first).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(block_on(second).ok(), Some(20));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_cache_waiters() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let cache = Cache::new();
+        let service = counted(calls.clone()).with(cache.clone());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut first = service.call(5);
+        let mut second = service.call(5);
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        for _ in 0..3 {
+            assert!(second.as_mut().poll(&mut cx).is_pending());
+        }
+        // One waker per waiting call, however often it is polled.
+        assert_eq!(cache.state.lock().unwrap().pending[&5].len(), 1);
+
+        assert_eq!(block_on(first).ok(), Some(10));
+        assert_eq!(block_on(second).ok(), Some(10));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let c = calls.clone();
+        let service = service!(move |req: u32| {
+            c.fetch_add(1, Ordering::SeqCst);
+            async move { Result::<_, Rejection<u32, ()>>::Ok(req) }
+        })
+        .with(Cache::new().ttl(Duration::from_millis(20)));
+
+        ready(service.call(1)).unwrap();
+        ready(service.call(1)).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        std::thread::sleep(Duration::from_millis(30));
+        ready(service.call(1)).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
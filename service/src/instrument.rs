@@ -0,0 +1,347 @@
+use crate::{Middleware, Rejection, Service};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::ready;
+use pin_project::pin_project;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Success,
+    /// The service rejected the request, see [`Rejection::Reject`].
+    Reject,
+    /// The service failed, see [`Rejection::Err`].
+    Error,
+}
+
+impl Outcome {
+    fn of<O, R, E>(ret: &Result<O, Rejection<R, E>>) -> Outcome {
+        match ret {
+            Ok(_) => Outcome::Success,
+            Err(Rejection::Reject(..)) => Outcome::Reject,
+            Err(Rejection::Err(_)) => Outcome::Error,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::Reject => write!(f, "reject"),
+            Outcome::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Receives every call of the services wrapped by an [`Instrument`].
+pub trait MetricsSink: Send + Sync {
+    fn record(&self, name: &str, outcome: Outcome, latency: Duration);
+}
+
+impl<F> MetricsSink for F
+where
+    F: Fn(&str, Outcome, Duration) + Send + Sync,
+{
+    fn record(&self, name: &str, outcome: Outcome, latency: Duration) {
+        (self)(name, outcome, latency)
+    }
+}
+
+/// Upper bounds of the buckets of a [`Histogram`], in microseconds.
+const BUCKETS: [u64; 16] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Latencies counted in buckets from 100µs to 10s, with one more bucket for anything slower.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: [u64; BUCKETS.len() + 1],
+    sum: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::from_secs(0),
+            n => self.sum / n as u32,
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile, like 0.99 for the 99th percentile.
+    /// Latencies over the last bucket report the maximum.
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = (self.count() as f64 * q.clamp(0.0, 1.0)).ceil() as u64;
+        let mut seen = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank.max(1) {
+                return match BUCKETS.get(idx) {
+                    Some(bound) => Duration::from_micros(*bound).min(self.max),
+                    None => self.max,
+                };
+            }
+        }
+        self.max
+    }
+
+    /// Buckets as their upper bound and count, the last without a bound.
+    pub fn buckets(&self) -> Vec<(Option<Duration>, u64)> {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(idx, count)| (BUCKETS.get(idx).map(|b| Duration::from_micros(*b)), *count))
+            .collect()
+    }
+}
+
+/// Counts of the calls of one instrumented service.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallMetrics {
+    pub calls: u64,
+    pub success: u64,
+    pub reject: u64,
+    pub error: u64,
+    pub latency: Histogram,
+}
+
+/// A [`MetricsSink`] keeping the metrics of every instrumented service in memory.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    services: Mutex<BTreeMap<String, CallMetrics>>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<CallMetrics> {
+        self.services.lock().unwrap().get(name).cloned()
+    }
+
+    /// Metrics of every service, by name.
+    pub fn snapshot(&self) -> BTreeMap<String, CallMetrics> {
+        self.services.lock().unwrap().clone()
+    }
+}
+
+impl MetricsSink for Metrics {
+    fn record(&self, name: &str, outcome: Outcome, latency: Duration) {
+        let mut services = self.services.lock().unwrap();
+        if !services.contains_key(name) {
+            services.insert(name.into(), CallMetrics::default());
+        }
+        let metrics = services.get_mut(name).unwrap();
+        metrics.calls += 1;
+        match outcome {
+            Outcome::Success => metrics.success += 1,
+            Outcome::Reject => metrics.reject += 1,
+            Outcome::Error => metrics.error += 1,
+        }
+        metrics.latency.record(latency);
+    }
+}
+
+impl<M: MetricsSink + ?Sized> MetricsSink for Arc<M> {
+    fn record(&self, name: &str, outcome: Outcome, latency: Duration) {
+        (**self).record(name, outcome, latency)
+    }
+}
+
+/// Records the outcome and latency of every call under a name.
+///
+/// Calls go to the [`MetricsSink`] given with [`Instrument::metrics`]. With the
+/// `tracing` feature every call also runs in a `service` span carrying the name,
+/// and logs its outcome and latency at debug level when it ends.
+///
+/// Wrapping the parts of a chain tells them apart:
+///
+/// ```
+/// # use service::{pass, Instrument, Metrics, ServiceExt};
+/// # use std::sync::Arc;
+/// let metrics = Arc::new(Metrics::new());
+/// let chain = pass::<(), ()>()
+///     .with(Instrument::new("auth").metrics(metrics.clone()))
+///     .or_else(pass::<(), ()>().with(Instrument::new("fallback").metrics(metrics.clone())));
+/// ```
+#[derive(Clone)]
+pub struct Instrument {
+    name: Arc<str>,
+    sink: Option<Arc<dyn MetricsSink>>,
+}
+
+impl Instrument {
+    pub fn new(name: &str) -> Instrument {
+        Instrument {
+            name: name.into(),
+            sink: None,
+        }
+    }
+
+    pub fn metrics(mut self, sink: impl MetricsSink + 'static) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+}
+
+impl<R, T> Middleware<R, T> for Instrument
+where
+    T: Service<R>,
+{
+    type Service = InstrumentService<T>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        InstrumentService {
+            service,
+            name: self.name.clone(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InstrumentService<T> {
+    service: T,
+    name: Arc<str>,
+    sink: Option<Arc<dyn MetricsSink>>,
+}
+
+impl<R, T> Service<R> for InstrumentService<T>
+where
+    T: Service<R>,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Future = InstrumentFuture<T::Future>;
+
+    fn call(&self, req: R) -> Self::Future {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("service", name = %self.name);
+        #[cfg(feature = "tracing")]
+        let fut = {
+            let _enter = span.enter();
+            self.service.call(req)
+        };
+        #[cfg(not(feature = "tracing"))]
+        let fut = self.service.call(req);
+
+        InstrumentFuture {
+            fut,
+            name: self.name.clone(),
+            sink: self.sink.clone(),
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+}
+
+#[pin_project]
+pub struct InstrumentFuture<F> {
+    #[pin]
+    fut: F,
+    name: Arc<str>,
+    sink: Option<Arc<dyn MetricsSink>>,
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<F, O, R, E> Future for InstrumentFuture<F>
+where
+    F: Future<Output = Result<O, Rejection<R, E>>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        #[cfg(feature = "tracing")]
+        let _enter = this.span.enter();
+
+        let ret = ready!(this.fut.poll(cx));
+        let outcome = Outcome::of(&ret);
+        let latency = this.start.elapsed();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%outcome, ?latency, "call finished");
+        if let Some(sink) = this.sink {
+            sink.record(this.name, outcome, latency);
+        }
+        Poll::Ready(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::ready;
+    use crate::{service, ServiceExt};
+
+    #[test]
+    fn test_instrument() {
+        let metrics = Arc::new(Metrics::new());
+        let service = service!(|req: u32| async move {
+            match req {
+                0 => Err(Rejection::Reject(req, None)),
+                1 => Err(Rejection::Err(())),
+                _ => Ok(req),
+            }
+        })
+        .with(Instrument::new("numbers").metrics(metrics.clone()));
+
+        for req in 0..5 {
+            let _ = ready(service.call(req));
+        }
+
+        let numbers = metrics.get("numbers").unwrap();
+        assert_eq!(
+            (
+                numbers.calls,
+                numbers.success,
+                numbers.reject,
+                numbers.error
+            ),
+            (5, 3, 1, 1)
+        );
+        assert_eq!(numbers.latency.count(), 5);
+        assert!(metrics.get("other").is_none());
+
+        let mut histogram = Histogram::default();
+        for ms in [1, 1, 2, 40, 2000] {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.quantile(0.4), Duration::from_millis(1));
+        assert_eq!(histogram.quantile(0.5), Duration::from_micros(2500));
+        assert_eq!(histogram.quantile(0.8), Duration::from_millis(50));
+        assert_eq!(histogram.quantile(1.0), Duration::from_millis(2000));
+        assert_eq!(histogram.max(), Duration::from_millis(2000));
+    }
+}
@@ -0,0 +1,152 @@
+use super::{Either, Rejection, Service};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use pin_project::pin_project;
+
+/// Calls both services at the same time with clones of the request, and returns both
+/// outputs. When one call fails, the other is dropped and the failure returned.
+#[derive(Clone)]
+pub struct Join<T1, T2> {
+    t1: T1,
+    t2: T2,
+}
+
+impl<T1, T2> Join<T1, T2> {
+    pub fn new(t1: T1, t2: T2) -> Join<T1, T2> {
+        Join { t1, t2 }
+    }
+}
+
+impl<T1, T2, R> Service<R> for Join<T1, T2>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+    R: Clone,
+    JoinFuture<T1, T2, R>: Send,
+{
+    type Output = (T1::Output, T2::Output);
+    type Error = Either<T1::Error, T2::Error>;
+    type Future = JoinFuture<T1, T2, R>;
+
+    fn call(&self, req: R) -> Self::Future {
+        JoinFuture {
+            first: Some(self.t1.call(req.clone())),
+            second: Some(self.t2.call(req)),
+            outputs: (None, None),
+        }
+    }
+}
+
+#[pin_project]
+pub struct JoinFuture<T1, T2, R>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+{
+    #[pin]
+    first: Option<T1::Future>,
+    #[pin]
+    second: Option<T2::Future>,
+    outputs: (Option<T1::Output>, Option<T2::Output>),
+}
+
+impl<T1, T2, R> Future for JoinFuture<T1, T2, R>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+{
+    #[allow(clippy::type_complexity)]
+    type Output = Result<(T1::Output, T2::Output), Rejection<R, Either<T1::Error, T2::Error>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.first.as_mut().as_pin_mut() {
+            if let Poll::Ready(ret) = fut.poll(cx) {
+                this.first.set(None);
+                match ret {
+                    Ok(ret) => this.outputs.0 = Some(ret),
+                    Err(err) => {
+                        this.second.set(None);
+                        return Poll::Ready(Err(match err {
+                            Rejection::Err(err) => Rejection::Err(Either::A(err)),
+                            Rejection::Reject(req, err) => {
+                                Rejection::Reject(req, err.map(Either::A))
+                            }
+                        }));
+                    }
+                }
+            }
+        }
+
+        if let Some(fut) = this.second.as_mut().as_pin_mut() {
+            if let Poll::Ready(ret) = fut.poll(cx) {
+                this.second.set(None);
+                match ret {
+                    Ok(ret) => this.outputs.1 = Some(ret),
+                    Err(err) => {
+                        this.first.set(None);
+                        return Poll::Ready(Err(match err {
+                            Rejection::Err(err) => Rejection::Err(Either::B(err)),
+                            Rejection::Reject(req, err) => {
+                                Rejection::Reject(req, err.map(Either::B))
+                            }
+                        }));
+                    }
+                }
+            }
+        }
+
+        match this.outputs {
+            (Some(_), Some(_)) => {
+                let (first, second) = (this.outputs.0.take(), this.outputs.1.take());
+                Poll::Ready(Ok((first.unwrap(), second.unwrap())))
+            }
+            _ => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{block_on, Delay};
+    use crate::{service, ServiceExt};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_join() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let d = dropped.clone();
+        let slow = service!(move |req: Arc<String>| {
+            let delay = Delay::new(10).on_drop(d.clone());
+            async move {
+                delay.await;
+                Result::<_, Rejection<Arc<String>, ()>>::Ok(req.len())
+            }
+        });
+        let fast = service!(|req: Arc<String>| async move {
+            Delay::new(2).await;
+            if req.is_empty() {
+                Err(Rejection::Err(()))
+            } else {
+                Ok(req.to_uppercase())
+            }
+        });
+
+        let join = slow.join(fast);
+        match block_on(join.call(Arc::new("abc".into()))) {
+            Ok((len, upper)) => assert_eq!((len, upper.as_str()), (3, "ABC")),
+            Err(_) => panic!("expected both outputs"),
+        }
+        assert!(!dropped.load(Ordering::SeqCst));
+
+        match block_on(join.call(Arc::new(String::new()))) {
+            Err(Rejection::Err(Either::B(()))) => {}
+            _ => panic!("expected the error of the second call"),
+        }
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
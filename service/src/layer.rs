@@ -0,0 +1,173 @@
+use super::{Middleware, Service};
+
+/// A middleware returning the service as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl<R, T: Service<R>> Middleware<R, T> for Identity {
+    type Service = T;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        service
+    }
+}
+
+/// Two middlewares applied as one, `outer` wrapping `inner`.
+#[derive(Debug, Clone, Copy)]
+pub struct Stack<I, O> {
+    inner: I,
+    outer: O,
+}
+
+impl<I, O> Stack<I, O> {
+    pub fn new(inner: I, outer: O) -> Stack<I, O> {
+        Stack { inner, outer }
+    }
+}
+
+impl<R, T, I, O> Middleware<R, T> for Stack<I, O>
+where
+    T: Service<R>,
+    I: Middleware<R, T>,
+    O: Middleware<R, I::Service>,
+{
+    type Service = O::Service;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        self.outer.wrap(self.inner.wrap(service))
+    }
+}
+
+/// Builds a stack of middlewares which is applied to services at once.
+///
+/// Middlewares run in the order they were added: the first is the outermost and
+/// sees a request first. A layer is a middleware itself, so a standard stack can be
+/// defined once and applied with [`ServiceExt::with`](crate::ServiceExt::with).
+///
+/// ```
+/// # use service::{pass, Instrument, Layer, Metrics, ServiceExt};
+/// # use std::sync::Arc;
+/// let metrics = Arc::new(Metrics::new());
+/// let layer = Layer::new()
+///     .push(Instrument::new("total").metrics(metrics.clone()))
+///     .push(Instrument::new("inner").metrics(metrics.clone()));
+///
+/// let service = pass::<(), ()>().with(layer.clone());
+/// let other = layer.service(pass::<(), ()>());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layer<M = Identity> {
+    middleware: M,
+}
+
+impl Layer {
+    pub fn new() -> Layer {
+        Layer {
+            middleware: Identity,
+        }
+    }
+}
+
+impl<M> Layer<M> {
+    /// Add `middleware` inside of the middlewares added so far.
+    pub fn push<N>(self, middleware: N) -> Layer<Stack<N, M>> {
+        Layer {
+            middleware: Stack::new(middleware, self.middleware),
+        }
+    }
+
+    /// Wrap `service` in every middleware of the layer.
+    pub fn service<R, T>(&self, service: T) -> M::Service
+    where
+        T: Service<R>,
+        M: Middleware<R, T>,
+    {
+        self.middleware.wrap(service)
+    }
+
+    pub fn into_inner(self) -> M {
+        self.middleware
+    }
+}
+
+impl<R, T, M> Middleware<R, T> for Layer<M>
+where
+    T: Service<R>,
+    M: Middleware<R, T>,
+{
+    type Service = M::Service;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        self.middleware.wrap(service)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::ready;
+    use crate::vec::MiddlewareStack;
+    use crate::{service, Rejection, ServiceExt};
+    use std::sync::{Arc, Mutex};
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    #[derive(Clone)]
+    struct Push(&'static str);
+
+    #[derive(Clone)]
+    struct PushService<T>(&'static str, T);
+
+    impl<T: Service<Log>> Middleware<Log, T> for Push {
+        type Service = PushService<T>;
+
+        fn wrap(&self, service: T) -> Self::Service {
+            PushService(self.0, service)
+        }
+    }
+
+    impl<T: Service<Log>> Service<Log> for PushService<T> {
+        type Output = T::Output;
+        type Error = T::Error;
+        type Future = T::Future;
+
+        fn call(&self, log: Log) -> Self::Future {
+            log.lock().unwrap().push(self.0);
+            self.1.call(log)
+        }
+    }
+
+    fn inner() -> impl Service<Log, Output = (), Error = ()> + Clone + Send + Sync {
+        service!(|log: Log| async move {
+            log.lock().unwrap().push("service");
+            Result::<_, Rejection<Log, ()>>::Ok(())
+        })
+    }
+
+    #[test]
+    fn test_layer() {
+        let layer = Layer::new().push(Push("a")).push(Push("b")).push(Identity);
+        let log = Log::default();
+        ready(inner().with(layer).call(log.clone())).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b", "service"]);
+
+        let layer = Layer::new().push(Push("c"));
+        let log = Log::default();
+        ready(layer.service(inner()).call(log.clone())).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["c", "service"]);
+    }
+
+    #[test]
+    fn test_middleware_stack() {
+        let mut stack = MiddlewareStack::new();
+        for name in ["a", "b", "c"] {
+            stack.push(Push(name));
+        }
+        assert_eq!(stack.len(), 3);
+
+        let log = Log::default();
+        let service = inner().with(stack);
+        ready(service.clone().call(log.clone())).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b", "c", "service"]);
+    }
+}
@@ -2,16 +2,36 @@
 
 extern crate alloc;
 
+// The timers of `runtime` panic without an executor to run them.
+#[cfg(all(
+    feature = "time",
+    not(any(feature = "tokio", feature = "smol", feature = "async-std"))
+))]
+compile_error!("the `time` feature needs an executor: enable `tokio`, `smol` or `async-std`");
+
 #[cfg(feature = "alloc")]
 mod boxed;
+#[cfg(feature = "std")]
+mod breaker;
+#[cfg(feature = "std")]
+mod cache;
 mod either;
 mod generic;
+#[cfg(feature = "std")]
+mod instrument;
+mod layer;
+#[cfg(feature = "time")]
+mod limit;
 mod macros;
 mod map;
 mod middleware;
 mod rejection;
+#[cfg(feature = "time")]
+mod retry;
 mod service;
 mod service_ext;
+#[cfg(feature = "time")]
+mod timeout;
 mod util;
 pub mod vec;
 
@@ -20,15 +40,30 @@ pub mod and_then;
 pub mod and_then_reject;
 pub mod err_into;
 pub mod flatten;
+pub mod join;
 pub mod map_err;
 pub mod or_else;
+pub mod race;
+pub mod router;
 pub mod then;
 pub mod unify;
 pub mod unpack;
 
+#[cfg(feature = "std")]
+pub use self::breaker::{
+    CircuitBreaker, CircuitBreakerService, CircuitFuture, CircuitOpen, CircuitState,
+};
+#[cfg(feature = "std")]
+pub use self::cache::{ByRequest, Cache, CacheKey, CacheService};
+#[cfg(feature = "std")]
+pub use self::instrument::{
+    CallMetrics, Histogram, Instrument, InstrumentFuture, InstrumentService, Metrics, MetricsSink,
+    Outcome,
+};
 pub use self::{
     either::*,
     generic::{one, Combine, Extract, Func, HList, One, Tuple},
+    layer::{Identity, Layer, Stack},
     map::*,
     middleware::*,
     rejection::*,
@@ -36,6 +71,12 @@ pub use self::{
     service_ext::*,
     util::*,
 };
+#[cfg(feature = "time")]
+pub use self::{
+    limit::{ConcurrencyLimit, ConcurrencyLimitService, RateLimit, RateLimitService},
+    retry::{Attempts, Backoff, OnError, Policy, Retry, RetryService},
+    timeout::{Elapsed, Timeout, TimeoutFuture, TimeoutService},
+};
 #[cfg(feature = "alloc")]
 pub use boxed::*;
 
@@ -46,6 +87,74 @@ pub mod prelude {
 #[cfg(test)]
 mod test {
     use super::*;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// Poll a future which never waits to completion.
+    pub(crate) fn ready<F: Future>(fut: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        match core::pin::pin!(fut).poll(&mut cx) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => panic!("future is pending"),
+        }
+    }
+
+    /// Poll a future until it completes, without waiting for wakeups.
+    pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut fut = core::pin::pin!(fut);
+        loop {
+            if let Poll::Ready(ret) = fut.as_mut().poll(&mut cx) {
+                return ret;
+            }
+        }
+    }
+
+    /// A future ready after being polled a number of times, which can flag being
+    /// dropped before that.
+    pub(crate) struct Delay {
+        polls: usize,
+        dropped: Option<Arc<AtomicBool>>,
+    }
+
+    impl Delay {
+        pub(crate) fn new(polls: usize) -> Delay {
+            Delay {
+                polls,
+                dropped: None,
+            }
+        }
+
+        pub(crate) fn on_drop(mut self, dropped: Arc<AtomicBool>) -> Delay {
+            self.dropped = Some(dropped);
+            self
+        }
+    }
+
+    impl Future for Delay {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.polls == 0 {
+                self.dropped = None;
+                return Poll::Ready(());
+            }
+            self.polls -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Delay {
+        fn drop(&mut self) {
+            if let Some(dropped) = &self.dropped {
+                dropped.store(true, Ordering::SeqCst);
+            }
+        }
+    }
 
     struct Param {}
 
@@ -0,0 +1,404 @@
+use crate::{BoxFuture, Middleware, Rejection, Service};
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::sync::Mutex;
+use std::time::Instant;
+
+struct Window {
+    start: Instant,
+    calls: u64,
+}
+
+/// Allows at most `calls` calls per period, delaying the calls over the limit.
+///
+/// The limit is shared by every service wrapped by the same `RateLimit` and its clones.
+#[derive(Clone)]
+pub struct RateLimit {
+    calls: u64,
+    per: Duration,
+    window: Arc<Mutex<Window>>,
+}
+
+impl RateLimit {
+    pub fn new(calls: u64, per: Duration) -> RateLimit {
+        RateLimit {
+            calls: calls.max(1),
+            per,
+            window: Arc::new(Mutex::new(Window {
+                start: Instant::now(),
+                calls: 0,
+            })),
+        }
+    }
+
+    /// Wait until a call is allowed and count it.
+    async fn ready(&self) {
+        loop {
+            let wait = {
+                let mut window = self.window.lock().unwrap();
+                let now = Instant::now();
+                if now >= window.start + self.per {
+                    window.start = now;
+                    window.calls = 0;
+                }
+                if window.calls < self.calls {
+                    window.calls += 1;
+                    return;
+                }
+                window.start + self.per - now
+            };
+            runtime::sleep(wait).await;
+        }
+    }
+}
+
+impl<R, T> Middleware<R, T> for RateLimit
+where
+    T: Service<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    type Service = RateLimitService<T>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        RateLimitService {
+            service: Arc::new(service),
+            limit: self.clone(),
+        }
+    }
+}
+
+pub struct RateLimitService<T> {
+    service: Arc<T>,
+    limit: RateLimit,
+}
+
+impl<T> Clone for RateLimitService<T> {
+    fn clone(&self) -> Self {
+        RateLimitService {
+            service: self.service.clone(),
+            limit: self.limit.clone(),
+        }
+    }
+}
+
+impl<R, T> Service<R> for RateLimitService<T>
+where
+    T: Service<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    #[allow(clippy::type_complexity)]
+    type Future = BoxFuture<'static, Result<Self::Output, Rejection<R, Self::Error>>>;
+
+    fn call(&self, req: R) -> Self::Future {
+        let service = self.service.clone();
+        let limit = self.limit.clone();
+        Box::pin(async move {
+            limit.ready().await;
+            service.call(req).await
+        })
+    }
+}
+
+struct Permits {
+    /// Free permits. None are free while calls are waiting.
+    available: usize,
+    /// Calls waiting for a permit, the longest waiting first.
+    waiters: VecDeque<(u64, Waker)>,
+    /// Woken calls holding a permit they have not taken yet.
+    granted: Vec<u64>,
+    next: u64,
+}
+
+impl Permits {
+    /// Hand a permit to the call waiting the longest, so that a new call cannot
+    /// take it first, or free it when no call is waiting.
+    fn release(&mut self) {
+        match self.waiters.pop_front() {
+            Some((id, waker)) => {
+                self.granted.push(id);
+                waker.wake();
+            }
+            None => self.available += 1,
+        }
+    }
+}
+
+/// Returns its permit when dropped, waking the call waiting the longest for one.
+struct Permit(Arc<Mutex<Permits>>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().release();
+    }
+}
+
+/// Waits for a permit, queued with a single waker until one is handed to it.
+struct Acquire {
+    permits: Arc<Mutex<Permits>>,
+    /// Place in the queue, once queued.
+    id: Option<u64>,
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Permit> {
+        let this = &mut *self;
+        let mut permits = this.permits.lock().unwrap();
+
+        if let Some(id) = this.id {
+            if let Some(index) = permits.granted.iter().position(|i| *i == id) {
+                permits.granted.swap_remove(index);
+                this.id = None;
+                return Poll::Ready(Permit(this.permits.clone()));
+            }
+            if let Some((_, waker)) = permits.waiters.iter_mut().find(|(i, _)| *i == id) {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            return Poll::Pending;
+        }
+
+        if permits.available > 0 {
+            permits.available -= 1;
+            return Poll::Ready(Permit(this.permits.clone()));
+        }
+
+        let id = permits.next;
+        permits.next += 1;
+        permits.waiters.push_back((id, cx.waker().clone()));
+        this.id = Some(id);
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        let id = match self.id {
+            Some(id) => id,
+            None => return,
+        };
+        let mut permits = self.permits.lock().unwrap();
+        if let Some(index) = permits.waiters.iter().position(|(i, _)| *i == id) {
+            permits.waiters.remove(index);
+        } else if let Some(index) = permits.granted.iter().position(|i| *i == id) {
+            // Granted a permit it will not take, pass it on.
+            permits.granted.swap_remove(index);
+            permits.release();
+        }
+    }
+}
+
+/// Allows at most `limit` calls in flight at the same time. Further calls wait for
+/// one of them to finish, in the order they started waiting.
+///
+/// The limit is shared by every service wrapped by the same `ConcurrencyLimit` and its clones.
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    permits: Arc<Mutex<Permits>>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(limit: usize) -> ConcurrencyLimit {
+        ConcurrencyLimit {
+            permits: Arc::new(Mutex::new(Permits {
+                available: limit.max(1),
+                waiters: VecDeque::new(),
+                granted: Vec::new(),
+                next: 0,
+            })),
+        }
+    }
+
+    fn acquire(&self) -> Acquire {
+        Acquire {
+            permits: self.permits.clone(),
+            id: None,
+        }
+    }
+}
+
+impl<R, T> Middleware<R, T> for ConcurrencyLimit
+where
+    T: Service<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    type Service = ConcurrencyLimitService<T>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        ConcurrencyLimitService {
+            service: Arc::new(service),
+            limit: self.clone(),
+        }
+    }
+}
+
+pub struct ConcurrencyLimitService<T> {
+    service: Arc<T>,
+    limit: ConcurrencyLimit,
+}
+
+impl<T> Clone for ConcurrencyLimitService<T> {
+    fn clone(&self) -> Self {
+        ConcurrencyLimitService {
+            service: self.service.clone(),
+            limit: self.limit.clone(),
+        }
+    }
+}
+
+impl<R, T> Service<R> for ConcurrencyLimitService<T>
+where
+    T: Service<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    #[allow(clippy::type_complexity)]
+    type Future = BoxFuture<'static, Result<Self::Output, Rejection<R, Self::Error>>>;
+
+    fn call(&self, req: R) -> Self::Future {
+        let service = self.service.clone();
+        let limit = self.limit.clone();
+        Box::pin(async move {
+            let _permit = limit.acquire().await;
+            service.call(req).await
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "tokio", feature = "smol", feature = "async-std")
+))]
+mod test {
+    use super::*;
+    use crate::service;
+    use crate::test::ready;
+    use core::future;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    /// Poll `futures` concurrently until all of them are done.
+    fn join_all<F: Future + Unpin>(mut futures: Vec<F>) -> Vec<F::Output> {
+        let mut out = futures.iter().map(|_| None).collect::<Vec<_>>();
+        runtime::block_on(future::poll_fn(|cx| {
+            for (future, out) in futures.iter_mut().zip(out.iter_mut()) {
+                if out.is_none() {
+                    if let Poll::Ready(ret) = core::pin::Pin::new(future).poll(cx) {
+                        *out = Some(ret);
+                    }
+                }
+            }
+            if out.iter().all(|ret| ret.is_some()) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }));
+        out.into_iter().map(|ret| ret.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let (r, m) = (running.clone(), max.clone());
+        let service = service!(move |req: u32| {
+            let (running, max) = (r.clone(), m.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                runtime::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Result::<_, Rejection<u32, ()>>::Ok(req)
+            }
+        });
+
+        let limit = ConcurrencyLimit::new(2);
+        let service = limit.wrap(service);
+        let out = join_all((0..6).map(|i| service.call(i)).collect());
+        assert_eq!(out.len(), 6);
+        assert!(out.iter().all(|ret| ret.is_ok()));
+        assert_eq!(max.load(Ordering::SeqCst), 2);
+    }
+
+    struct CountWakes(AtomicUsize);
+
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_concurrency_limit_wakers() {
+        let limit = ConcurrencyLimit::new(1);
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let permit = ready(limit.acquire());
+        let mut waiters = (0..3)
+            .map(|_| Box::pin(limit.acquire()))
+            .collect::<Vec<_>>();
+        for _ in 0..3 {
+            for waiter in &mut waiters {
+                assert!(waiter.as_mut().poll(&mut cx).is_pending());
+            }
+        }
+        // One waker per waiter, however often it is polled.
+        assert_eq!(limit.permits.lock().unwrap().waiters.len(), 3);
+
+        // A released permit wakes the first waiter only.
+        drop(permit);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        // Dropped without taking it, the permit goes to the next waiter.
+        waiters.remove(0);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 2);
+        let permit = waiters[0].as_mut().poll(&mut cx);
+        assert!(permit.is_ready());
+        assert!(waiters[1].as_mut().poll(&mut cx).is_pending());
+        assert_eq!(limit.permits.lock().unwrap().waiters.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrency_limit_order() {
+        let limit = ConcurrencyLimit::new(1);
+        let waker = Waker::from(Arc::new(CountWakes(AtomicUsize::new(0))));
+        let mut cx = Context::from_waker(&waker);
+
+        let permit = ready(limit.acquire());
+        let mut waiter = Box::pin(limit.acquire());
+        assert!(waiter.as_mut().poll(&mut cx).is_pending());
+
+        // A call started after the permit is released still waits behind the woken one.
+        drop(permit);
+        let mut late = Box::pin(limit.acquire());
+        assert!(late.as_mut().poll(&mut cx).is_pending());
+        let permit = waiter.as_mut().poll(&mut cx);
+        assert!(permit.is_ready());
+        assert!(late.as_mut().poll(&mut cx).is_pending());
+
+        drop(permit);
+        assert!(late.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let service = service!(|req: u32| async move { Result::<_, Rejection<u32, ()>>::Ok(req) });
+        let service = RateLimit::new(2, Duration::from_millis(50)).wrap(service);
+
+        let start = Instant::now();
+        let out = join_all((0..5).map(|i| service.call(i)).collect());
+        assert!(out.iter().all(|ret| ret.is_ok()));
+        // Two calls in each of the first two windows, the fifth in the third.
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
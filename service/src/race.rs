@@ -0,0 +1,164 @@
+use super::{Either, Rejection, Service};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use pin_project::pin_project;
+
+/// Calls both services at the same time with clones of the request, and returns the
+/// first success. The other call is dropped as soon as one succeeds.
+#[derive(Clone)]
+pub struct Race<T1, T2> {
+    t1: T1,
+    t2: T2,
+}
+
+impl<T1, T2> Race<T1, T2> {
+    pub fn new(t1: T1, t2: T2) -> Race<T1, T2> {
+        Race { t1, t2 }
+    }
+}
+
+impl<T1, T2, R> Service<R> for Race<T1, T2>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+    R: Clone,
+    RaceFuture<T1, T2, R>: Send,
+{
+    type Output = Either<T1::Output, T2::Output>;
+    type Error = Either<T1::Error, T2::Error>;
+    type Future = RaceFuture<T1, T2, R>;
+
+    fn call(&self, req: R) -> Self::Future {
+        RaceFuture {
+            first: Some(self.t1.call(req.clone())),
+            second: Some(self.t2.call(req)),
+            failure: None,
+        }
+    }
+}
+
+#[pin_project]
+pub struct RaceFuture<T1, T2, R>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+{
+    #[pin]
+    first: Option<T1::Future>,
+    #[pin]
+    second: Option<T2::Future>,
+    #[allow(clippy::type_complexity)]
+    failure: Option<Rejection<R, Either<T1::Error, T2::Error>>>,
+}
+
+/// Keep the failure to return when both calls fail. Errors win over rejections,
+/// otherwise the first failure is kept.
+fn fail<R, E>(failure: &mut Option<Rejection<R, E>>, rejection: Rejection<R, E>) {
+    match (&failure, &rejection) {
+        (None, _) | (Some(Rejection::Reject(..)), Rejection::Err(_)) => *failure = Some(rejection),
+        _ => {}
+    }
+}
+
+impl<T1, T2, R> Future for RaceFuture<T1, T2, R>
+where
+    T1: Service<R>,
+    T2: Service<R>,
+{
+    #[allow(clippy::type_complexity)]
+    type Output =
+        Result<Either<T1::Output, T2::Output>, Rejection<R, Either<T1::Error, T2::Error>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.first.as_mut().as_pin_mut() {
+            if let Poll::Ready(ret) = fut.poll(cx) {
+                this.first.set(None);
+                match ret {
+                    Ok(ret) => {
+                        this.second.set(None);
+                        return Poll::Ready(Ok(Either::A(ret)));
+                    }
+                    Err(Rejection::Err(err)) => fail(this.failure, Rejection::Err(Either::A(err))),
+                    Err(Rejection::Reject(req, err)) => {
+                        fail(this.failure, Rejection::Reject(req, err.map(Either::A)))
+                    }
+                }
+            }
+        }
+
+        if let Some(fut) = this.second.as_mut().as_pin_mut() {
+            if let Poll::Ready(ret) = fut.poll(cx) {
+                this.second.set(None);
+                match ret {
+                    Ok(ret) => {
+                        this.first.set(None);
+                        return Poll::Ready(Ok(Either::B(ret)));
+                    }
+                    Err(Rejection::Err(err)) => fail(this.failure, Rejection::Err(Either::B(err))),
+                    Err(Rejection::Reject(req, err)) => {
+                        fail(this.failure, Rejection::Reject(req, err.map(Either::B)))
+                    }
+                }
+            }
+        }
+
+        if this.first.is_none() && this.second.is_none() {
+            Poll::Ready(Err(this.failure.take().expect("poll after done")))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{block_on, Delay};
+    use crate::{service, ServiceExt};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_race() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let d = dropped.clone();
+        let slow = service!(move |req: u32| {
+            let delay = Delay::new(10).on_drop(d.clone());
+            async move {
+                delay.await;
+                Result::<_, Rejection<u32, ()>>::Ok(req * 10)
+            }
+        });
+        let fast = service!(|req: u32| async move {
+            Delay::new(2).await;
+            match req {
+                0 => Err(Rejection::Reject(req, None)),
+                1 => Err(Rejection::Err(())),
+                _ => Ok(req),
+            }
+        });
+
+        let race = slow.race(fast);
+        assert_eq!(block_on(race.call(2)).ok(), Some(Either::B(2)));
+        assert!(dropped.load(Ordering::SeqCst));
+
+        // A failure of one call waits for the other.
+        assert_eq!(block_on(race.call(0)).ok(), Some(Either::A(0)));
+        assert_eq!(block_on(race.call(1)).ok(), Some(Either::A(10)));
+
+        let fails = service!(|req: u32| async move {
+            Result::<u32, _>::Err(Rejection::Reject(req, Some("no")))
+        });
+        let errs = service!(|_: u32| async move {
+            Delay::new(1).await;
+            Result::<u32, _>::Err(Rejection::Err("failed"))
+        });
+        match block_on(fails.race(errs).call(3)) {
+            Err(Rejection::Err(Either::B("failed"))) => {}
+            _ => panic!("expected the error of the second call"),
+        }
+    }
+}
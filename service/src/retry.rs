@@ -0,0 +1,262 @@
+use crate::{BoxFuture, Middleware, Rejection, Service};
+use alloc::{boxed::Box, sync::Arc};
+use core::time::Duration;
+
+/// Delay between the attempts of a [`Retry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// Retry right away.
+    #[default]
+    None,
+    Fixed(Duration),
+    /// Double the delay after every attempt, starting at `initial`, up to `max`.
+    Exponential {
+        initial: Duration,
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Delay before retry number `retry`, starting at 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Backoff::None => Duration::from_secs(0),
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32
+                    .checked_shl(retry.saturating_sub(1))
+                    .unwrap_or(u32::MAX);
+                initial.checked_mul(factor).unwrap_or(max).min(max)
+            }
+        }
+    }
+}
+
+/// Decides whether a failed call is tried again.
+pub trait Policy<R, E> {
+    /// Delay before trying again after attempt number `attempt`, starting at 1,
+    /// failed with `rejection`. `None` gives up and returns `rejection`.
+    fn retry(&self, attempt: u32, rejection: &Rejection<R, E>) -> Option<Duration>;
+}
+
+/// Retries calls failing with [`Rejection::Err`]. Rejected requests are passed on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OnError;
+
+/// Retry up to a number of times with a backoff, when a predicate holds for the rejection.
+#[derive(Debug, Clone, Copy)]
+pub struct Attempts<F = OnError> {
+    retries: u32,
+    backoff: Backoff,
+    predicate: F,
+}
+
+impl Attempts {
+    pub fn new(retries: u32) -> Attempts {
+        Attempts {
+            retries,
+            backoff: Backoff::None,
+            predicate: OnError,
+        }
+    }
+}
+
+impl<F> Attempts<F> {
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Only retry rejections `predicate` returns true for.
+    pub fn when<G>(self, predicate: G) -> Attempts<G> {
+        Attempts {
+            retries: self.retries,
+            backoff: self.backoff,
+            predicate,
+        }
+    }
+
+    fn delay(&self, attempt: u32, retry: bool) -> Option<Duration> {
+        if retry && attempt <= self.retries {
+            Some(self.backoff.delay(attempt))
+        } else {
+            None
+        }
+    }
+}
+
+impl<R, E> Policy<R, E> for Attempts<OnError> {
+    fn retry(&self, attempt: u32, rejection: &Rejection<R, E>) -> Option<Duration> {
+        self.delay(attempt, matches!(rejection, Rejection::Err(_)))
+    }
+}
+
+impl<R, E, F> Policy<R, E> for Attempts<F>
+where
+    F: Fn(&Rejection<R, E>) -> bool,
+{
+    fn retry(&self, attempt: u32, rejection: &Rejection<R, E>) -> Option<Duration> {
+        self.delay(attempt, (self.predicate)(rejection))
+    }
+}
+
+/// Calls the wrapped service again while its [`Policy`] allows it.
+///
+/// Every attempt gets a clone of the request.
+#[derive(Debug)]
+pub struct Retry<P> {
+    policy: Arc<P>,
+}
+
+impl<P> Retry<P> {
+    pub fn new(policy: P) -> Retry<P> {
+        Retry {
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<P> Clone for Retry<P> {
+    fn clone(&self) -> Self {
+        Retry {
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<R, T, P> Middleware<R, T> for Retry<P>
+where
+    T: Service<R> + Send + Sync + 'static,
+    T::Output: Send,
+    T::Error: Send,
+    R: Clone + Send + 'static,
+    P: Policy<R, T::Error> + Send + Sync + 'static,
+{
+    type Service = RetryService<T, P>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        RetryService {
+            service: Arc::new(service),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+pub struct RetryService<T, P> {
+    service: Arc<T>,
+    policy: Arc<P>,
+}
+
+impl<T, P> Clone for RetryService<T, P> {
+    fn clone(&self) -> Self {
+        RetryService {
+            service: self.service.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<R, T, P> Service<R> for RetryService<T, P>
+where
+    T: Service<R> + Send + Sync + 'static,
+    T::Output: Send,
+    T::Error: Send,
+    R: Clone + Send + 'static,
+    P: Policy<R, T::Error> + Send + Sync + 'static,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    #[allow(clippy::type_complexity)]
+    type Future = BoxFuture<'static, Result<Self::Output, Rejection<R, Self::Error>>>;
+
+    fn call(&self, req: R) -> Self::Future {
+        let service = self.service.clone();
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let mut attempt = 1;
+            loop {
+                let rejection = match service.call(req.clone()).await {
+                    Ok(ret) => return Ok(ret),
+                    Err(rejection) => rejection,
+                };
+                match policy.retry(attempt, &rejection) {
+                    Some(delay) => {
+                        if delay > Duration::from_secs(0) {
+                            runtime::sleep(delay).await;
+                        }
+                        attempt += 1;
+                    }
+                    None => return Err(rejection),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "tokio", feature = "smol", feature = "async-std")
+))]
+mod test {
+    use super::*;
+    use crate::service;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    fn flaky(
+        calls: Arc<AtomicU32>,
+        fails: u32,
+    ) -> impl Service<u32, Output = u32, Error = &'static str> + Send + Sync + 'static {
+        service!(move |req: u32| {
+            let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if req == 0 {
+                    Err(Rejection::Reject(req, None))
+                } else if call <= fails {
+                    Err(Rejection::Err("failed"))
+                } else {
+                    Ok(call)
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_retry() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let retry = Retry::new(Attempts::new(2).backoff(Backoff::Fixed(Duration::from_millis(1))));
+        let service = retry.wrap(flaky(calls.clone(), 2));
+        assert_eq!(runtime::block_on(service.call(1)).unwrap(), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let service = Retry::new(Attempts::new(1)).wrap(flaky(calls.clone(), 2));
+        assert!(matches!(
+            runtime::block_on(service.call(1)),
+            Err(Rejection::Err("failed"))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Rejections are not retried by default, but can be.
+        calls.store(0, Ordering::SeqCst);
+        let service = Retry::new(Attempts::new(3)).wrap(flaky(calls.clone(), 0));
+        assert!(runtime::block_on(service.call(0)).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        calls.store(0, Ordering::SeqCst);
+        let policy = Attempts::new(3).when(|_: &Rejection<u32, &'static str>| true);
+        let service = Retry::new(policy).wrap(flaky(calls.clone(), 0));
+        assert!(runtime::block_on(service.call(0)).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(10));
+        assert_eq!(backoff.delay(3), Duration::from_millis(40));
+        assert_eq!(backoff.delay(4), Duration::from_millis(50));
+        assert_eq!(backoff.delay(64), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,217 @@
+use super::{Rejection, Service};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
+use futures_core::future::BoxFuture;
+
+#[derive(Clone, Debug)]
+struct Node<K, T> {
+    exact: Vec<T>,
+    prefix: Vec<T>,
+    children: BTreeMap<K, Node<K, T>>,
+}
+
+impl<K: Ord, T> Node<K, T> {
+    fn new() -> Node<K, T> {
+        Node {
+            exact: Vec::new(),
+            prefix: Vec::new(),
+            children: BTreeMap::new(),
+        }
+    }
+
+    fn find<P>(&mut self, path: P) -> &mut Node<K, T>
+    where
+        P: IntoIterator,
+        P::Item: Into<K>,
+    {
+        path.into_iter().fold(self, |node, key| {
+            node.children.entry(key.into()).or_insert_with(Node::new)
+        })
+    }
+
+    /// Services matching `path`, most specific first: the exact routes of `path`,
+    /// then the prefix routes from the longest matching prefix to the root.
+    fn candidates(&self, path: Vec<K>) -> Vec<&T> {
+        let mut nodes = Vec::with_capacity(path.len() + 1);
+        nodes.push(self);
+        for key in &path {
+            match nodes[nodes.len() - 1].children.get(key) {
+                Some(child) => nodes.push(child),
+                None => break,
+            }
+        }
+
+        let mut out = Vec::new();
+        if nodes.len() == path.len() + 1 {
+            out.extend(nodes[nodes.len() - 1].exact.iter());
+        }
+        for node in nodes.iter().rev() {
+            out.extend(node.prefix.iter());
+        }
+        out
+    }
+}
+
+/// Dispatches requests on keys taken from them, through a trie of routes.
+///
+/// `key` returns the path of a request, like the segments of an url, or a single key
+/// like a method or an id by returning an `Option` or an array. Routes are either exact,
+/// matching only their path, or prefixes, matching their path and everything below.
+/// Only the services matching a request are called: exact routes first, then prefixes
+/// from the longest. Rejections fall through to the next match, as with
+/// [`VecService`](crate::vec::VecService), and a request nothing accepts is rejected.
+///
+/// ```
+/// # use service::{box_service, router::Router, Rejection};
+/// let reply = |name: &'static str| {
+///     box_service(service::service!(move |path: String| async move {
+///         Result::<_, Rejection<String, ()>>::Ok(format!("{}: {}", name, path))
+///     }))
+/// };
+///
+/// let router = Router::<String, _, _>::new(|path: &String| {
+///     path.split('/').filter(|s| !s.is_empty()).map(String::from).collect::<Vec<_>>()
+/// })
+/// .route(["users"], reply("list users"))
+/// .prefix(["users"], reply("user"))
+/// .fallback(reply("not found"));
+/// ```
+pub struct Router<K, T, F> {
+    root: Arc<Node<K, T>>,
+    key: Arc<F>,
+}
+
+impl<K: Ord, T, F> Router<K, T, F> {
+    pub fn new(key: F) -> Router<K, T, F> {
+        Router {
+            root: Arc::new(Node::new()),
+            key: Arc::new(key),
+        }
+    }
+}
+
+impl<K, T, F> Router<K, T, F>
+where
+    K: Ord + Clone,
+    T: Clone,
+{
+    /// Route requests with exactly `path` to `service`.
+    pub fn route<P>(mut self, path: P, service: T) -> Self
+    where
+        P: IntoIterator,
+        P::Item: Into<K>,
+    {
+        Arc::make_mut(&mut self.root).find(path).exact.push(service);
+        self
+    }
+
+    /// Route requests with a path starting with `path` to `service`.
+    pub fn prefix<P>(mut self, path: P, service: T) -> Self
+    where
+        P: IntoIterator,
+        P::Item: Into<K>,
+    {
+        Arc::make_mut(&mut self.root)
+            .find(path)
+            .prefix
+            .push(service);
+        self
+    }
+
+    /// Route every request to `service`, after all other routes.
+    pub fn fallback(self, service: T) -> Self {
+        self.prefix(None::<K>, service)
+    }
+}
+
+impl<K, T, F> Clone for Router<K, T, F> {
+    fn clone(&self) -> Self {
+        Router {
+            root: self.root.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl<K, T, F, I, R> Service<R> for Router<K, T, F>
+where
+    K: Ord + Send + Sync + 'static,
+    T: Service<R> + Send + Sync + 'static,
+    F: Fn(&R) -> I + Send + Sync + 'static,
+    I: IntoIterator<Item = K>,
+    R: Send + 'static,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Future = BoxFuture<'static, Result<Self::Output, Rejection<R, Self::Error>>>;
+
+    fn call(&self, mut req: R) -> Self::Future {
+        let path = (self.key)(&req).into_iter().collect::<Vec<_>>();
+        let root = self.root.clone();
+        let fut = async move {
+            for service in root.candidates(path) {
+                match service.call(req).await {
+                    Ok(ret) => return Ok(ret),
+                    Err(Rejection::Err(err)) => return Err(Rejection::Err(err)),
+                    Err(Rejection::Reject(r, _)) => {
+                        req = r;
+                    }
+                }
+            }
+
+            Err(Rejection::Reject(req, None))
+        };
+
+        Box::pin(fut)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::ready;
+    use crate::{box_service, BoxService};
+    use alloc::string::String;
+
+    fn reply(name: &'static str, accept: bool) -> BoxService<'static, String, String, ()> {
+        box_service(crate::service!(move |req: String| async move {
+            if accept {
+                Ok(format!("{} {}", name, req))
+            } else {
+                Err(Rejection::Reject(req, None))
+            }
+        }))
+    }
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_router() {
+        let router = Router::new(|path: &String| segments(path))
+            .route(["users"], reply("list", true))
+            .route(["users", "admin"], reply("skip", false))
+            .prefix(["users"], reply("user", true))
+            .fallback(reply("fallback", true));
+
+        let call = |path: &str| ready(router.call(path.into())).unwrap();
+        assert_eq!(call("/users"), "list /users");
+        assert_eq!(call("/users/1/posts"), "user /users/1/posts");
+        assert_eq!(call("/users/admin"), "user /users/admin");
+        assert_eq!(call("/"), "fallback /");
+        assert_eq!(call("/posts"), "fallback /posts");
+
+        let router = Router::new(|req: &String| req.split(' ').next().map(String::from))
+            .route(["GET"], reply("get", true))
+            .route(["PUT"], reply("put", false));
+        assert_eq!(ready(router.call("GET /".into())).unwrap(), "get GET /");
+        match ready(router.call("PUT /".into())) {
+            Err(Rejection::Reject(req, None)) => assert_eq!(req, "PUT /"),
+            _ => panic!("expected rejection"),
+        }
+        assert!(ready(router.call("POST /".into())).is_err());
+    }
+}
@@ -2,8 +2,9 @@
 use super::boxed::{box_service, BoxService};
 use super::{
     and::And, and_then::AndThen, and_then_reject::AndThenReject, err_into::ErrInto,
-    flatten::Flatten, map::Map, map_err::MapErr, or_else::OrElse, then::Then, unify::Unify,
-    unpack::Unpack, Combine, Either, Extract, Func, Middleware, Service, Tuple,
+    flatten::Flatten, join::Join, map::Map, map_err::MapErr, or_else::OrElse, race::Race,
+    then::Then, unify::Unify, unpack::Unpack, Combine, Either, Extract, Func, Middleware, Service,
+    Tuple,
 };
 use futures_core::TryFuture;
 pub trait ServiceExt<R>: Service<R> + Sized {
@@ -15,6 +16,24 @@ pub trait ServiceExt<R>: Service<R> + Sized {
         OrElse::new(self, task)
     }
 
+    /// Call both services at the same time, returning the first success.
+    /// The request is cloned, so share large requests through an `Arc`.
+    fn race<T: Service<R>>(self, task: T) -> Race<Self, T>
+    where
+        R: Clone,
+    {
+        Race::new(self, task)
+    }
+
+    /// Call both services at the same time, returning both outputs.
+    /// The request is cloned, so share large requests through an `Arc`.
+    fn join<T: Service<R>>(self, task: T) -> Join<Self, T>
+    where
+        R: Clone,
+    {
+        Join::new(self, task)
+    }
+
     fn unify<T, E>(self) -> Unify<Self>
     where
         Self: Service<R, Output = Either<T, T>, Error = Either<E, E>> + Sized,
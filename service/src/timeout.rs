@@ -0,0 +1,132 @@
+use crate::{Middleware, Rejection, Service};
+use alloc::boxed::Box;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use pin_project::pin_project;
+
+/// Error of a call which did not finish within the duration of a [`Timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed(pub Duration);
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out after {:?}", self.0)
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Fails calls taking longer than a duration with [`Elapsed`],
+/// converted into the error of the wrapped service.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout {
+    duration: Duration,
+}
+
+impl Timeout {
+    pub fn new(duration: Duration) -> Timeout {
+        Timeout { duration }
+    }
+}
+
+impl<R, T> Middleware<R, T> for Timeout
+where
+    T: Service<R>,
+    T::Error: From<Elapsed>,
+{
+    type Service = TimeoutService<T>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        TimeoutService {
+            service,
+            duration: self.duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeoutService<T> {
+    service: T,
+    duration: Duration,
+}
+
+impl<R, T> Service<R> for TimeoutService<T>
+where
+    T: Service<R>,
+    T::Error: From<Elapsed>,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Future = TimeoutFuture<T::Future>;
+
+    fn call(&self, req: R) -> Self::Future {
+        TimeoutFuture {
+            fut: self.service.call(req),
+            sleep: Box::pin(runtime::sleep(self.duration)),
+            duration: self.duration,
+        }
+    }
+}
+
+#[pin_project]
+pub struct TimeoutFuture<F> {
+    #[pin]
+    fut: F,
+    sleep: Pin<Box<dyn Future<Output = ()> + Send>>,
+    duration: Duration,
+}
+
+impl<F, O, R, E> Future for TimeoutFuture<F>
+where
+    F: Future<Output = Result<O, Rejection<R, E>>>,
+    E: From<Elapsed>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(ret) = this.fut.poll(cx) {
+            return Poll::Ready(ret);
+        }
+        match this.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Rejection::Err(Elapsed(*this.duration).into()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "tokio", feature = "smol", feature = "async-std")
+))]
+mod test {
+    use super::*;
+    use crate::{service, Rejection};
+
+    #[derive(Debug, PartialEq)]
+    struct Error(Elapsed);
+
+    impl From<Elapsed> for Error {
+        fn from(elapsed: Elapsed) -> Self {
+            Error(elapsed)
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        let sleep = service!(|ms: u64| async move {
+            runtime::sleep(Duration::from_millis(ms)).await;
+            Result::<_, Rejection<u64, Error>>::Ok(ms)
+        });
+        let service = Timeout::new(Duration::from_millis(20)).wrap(sleep);
+
+        assert_eq!(runtime::block_on(service.call(1)).unwrap(), 1);
+        match runtime::block_on(service.call(1000)) {
+            Err(Rejection::Err(err)) => assert_eq!(err, Error(Elapsed(Duration::from_millis(20)))),
+            _ => panic!("expected timeout"),
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::{Middleware, Rejection, Service};
+use super::{
+    box_middleware, box_service, BoxMiddleware, BoxService, DynamicMiddleware, Middleware,
+    Rejection, Service,
+};
 use alloc::{sync::Arc, vec::Vec};
 use futures_core::future::BoxFuture;
 
@@ -43,6 +46,64 @@ where
     }
 }
 
-// pub struct MiddlewareStack<M> {
-//     stack: Arc<Vec<M>>,
-// }
+/// A list of middlewares of any type, applied to a service at once.
+///
+/// Middlewares run in the order they were pushed: the first is the outermost.
+/// Services are boxed, which allows building the list at runtime, for example from
+/// configuration.
+pub struct MiddlewareStack<'a, I, O, E> {
+    stack: Vec<BoxMiddleware<'a, I, O, E>>,
+}
+
+impl<'a, I, O, E> MiddlewareStack<'a, I, O, E> {
+    pub fn new() -> Self {
+        MiddlewareStack { stack: Vec::new() }
+    }
+
+    pub fn push<M>(&mut self, middleware: M) -> &mut Self
+    where
+        M: DynamicMiddleware<'a, I, O, E> + 'a,
+    {
+        self.stack.push(box_middleware(middleware));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+}
+
+impl<'a, I, O, E> Default for MiddlewareStack<'a, I, O, E> {
+    fn default() -> Self {
+        MiddlewareStack::new()
+    }
+}
+
+impl<'a, I, O, E> Clone for MiddlewareStack<'a, I, O, E> {
+    fn clone(&self) -> Self {
+        MiddlewareStack {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+impl<'a, I, O, E, T> Middleware<I, T> for MiddlewareStack<'a, I, O, E>
+where
+    T: Service<I, Output = O, Error = E> + Clone + Send + Sync + 'a,
+    T::Future: 'a,
+{
+    type Service = BoxService<'a, I, O, E>;
+
+    fn wrap(&self, service: T) -> Self::Service {
+        self.stack
+            .iter()
+            .rev()
+            .fold(box_service(service), |service, middleware| {
+                middleware.wrap_boxed(service)
+            })
+    }
+}
@@ -25,6 +25,12 @@ impl std::ops::Deref for CacheKey {
 
 pub struct CacheSetOptions {}
 
+/// Storage of the files produced by the assets pipeline.
+///
+/// This is not `service::Cache`: the pipeline runs on the `Task` trait of `tasks`,
+/// not on `service::Service`, and files are not `Clone`. Their content is a stream
+/// which is read into memory when stored, so every hit hands out a new `File` over
+/// the stored bytes. Moving to `service::Cache` waits for the assets to move to `service`.
 pub trait Cache: Sync + Send {
     fn set<'a>(
         &'a self,